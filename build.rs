@@ -11,6 +11,7 @@ fn init_proto() -> Result<(), Box<dyn std::error::Error>> {
     );
 
     let files = &[
+        "proto/ratings_features_admin.proto",
         "proto/ratings_features_app.proto",
         "proto/ratings_features_chart.proto",
         "proto/ratings_features_user.proto",
@@ -22,17 +23,8 @@ fn init_proto() -> Result<(), Box<dyn std::error::Error>> {
         .build_server(true)
         .file_descriptor_set_path(descriptor_set_path)
         .out_dir(out_dir)
-        .type_attribute("Category", "#[derive(sqlx::Type, strum::EnumString)]")
-        .type_attribute(
-            "Category",
-            r#"#[strum(serialize_all = "kebab_case", ascii_case_insensitive)]"#,
-        )
         .compile(files, &["proto"])?;
 
-    if std::env::var("SKIP_CACHE").is_ok() {
-        println!("cargo:rustc-cfg=feature=\"skip_cache\"");
-    }
-
     Ok(())
 }
 
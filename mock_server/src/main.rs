@@ -80,7 +80,7 @@ async fn snap_assertions(
     match guard.id_map.get(&snap_id) {
         Some(name) => (
             StatusCode::OK,
-            json!({ "headers": { "snap-name": name } }).to_string(),
+            json!({ "headers": { "snap-name": name, "publisher-id": "mock-publisher" } }).to_string(),
         ),
 
         None => {
@@ -0,0 +1,6 @@
+-- Clients that have been banned by an admin are no longer able to authenticate or vote
+
+CREATE TABLE banned_clients (
+    client_hash CHAR(64) PRIMARY KEY, -- sha256([$user:$machineId])
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+-- Metadata about snaps from snapcraft.io, refreshed periodically rather than fetched on every
+-- request
+
+CREATE TABLE snaps (
+    snap_id CHAR(32) PRIMARY KEY,
+    name TEXT NOT NULL,
+    publisher TEXT,
+    last_refreshed TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
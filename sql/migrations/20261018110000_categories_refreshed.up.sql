@@ -0,0 +1,4 @@
+-- Track when the categories for each snap were last synced with snapcraft.io so that they can
+-- be refreshed in the background
+
+ALTER TABLE snaps ADD COLUMN categories_refreshed TIMESTAMPTZ;
@@ -0,0 +1,40 @@
+-- Move from a fixed set of categories to a table of categories identified by their slug in the
+-- store, so that new store categories can be added without a release.
+--
+-- The IDs of the existing categories are preserved so that they continue to match the values of
+-- the Category enum in the chart proto.
+
+CREATE TABLE categories (
+    id SERIAL PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL
+);
+
+INSERT INTO categories (id, slug, name) VALUES
+    (0, 'art-and-design', 'Art and Design'),
+    (1, 'books-and-reference', 'Books and Reference'),
+    (2, 'development', 'Development'),
+    (3, 'devices-and-iot', 'Devices and IoT'),
+    (4, 'education', 'Education'),
+    (5, 'entertainment', 'Entertainment'),
+    (6, 'featured', 'Featured'),
+    (7, 'finance', 'Finance'),
+    (8, 'games', 'Games'),
+    (9, 'health-and-fitness', 'Health and Fitness'),
+    (10, 'music-and-audio', 'Music and Audio'),
+    (11, 'news-and-weather', 'News and Weather'),
+    (12, 'personalisation', 'Personalisation'),
+    (13, 'photo-and-video', 'Photo and Video'),
+    (14, 'productivity', 'Productivity'),
+    (15, 'science', 'Science'),
+    (16, 'security', 'Security'),
+    (17, 'server-and-cloud', 'Server and Cloud'),
+    (18, 'social', 'Social'),
+    (19, 'utilities', 'Utilities');
+
+SELECT setval('categories_id_seq', (SELECT MAX(id) FROM categories));
+
+ALTER TABLE snap_categories DROP CONSTRAINT category;
+ALTER TABLE snap_categories
+    ADD CONSTRAINT snap_categories_category_fkey
+    FOREIGN KEY (category) REFERENCES categories(id) ON DELETE CASCADE;
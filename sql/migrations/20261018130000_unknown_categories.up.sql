@@ -0,0 +1,12 @@
+-- Category slugs returned by snapcraft.io that do not match any of our categories are kept
+-- here until the category is added, rather than being silently dropped
+
+CREATE TABLE unknown_categories (
+    snap_id CHAR(32) NOT NULL,
+    slug TEXT NOT NULL,
+    first_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (snap_id, slug)
+);
+
+CREATE INDEX unknown_categories_slug ON unknown_categories (slug);
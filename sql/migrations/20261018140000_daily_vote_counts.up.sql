@@ -0,0 +1,51 @@
+-- Per snap daily vote counts, used by chart and bulk rating queries so that they do not need to
+-- scan the full votes table. The counts are kept in sync with the votes table by a trigger so
+-- every write to votes, including cascading deletes of users, is reflected here.
+
+CREATE TABLE daily_vote_counts (
+    snap_id CHAR(32) NOT NULL,
+    day DATE NOT NULL, -- the UTC day that the votes were cast on
+    total_votes INT NOT NULL,
+    positive_votes INT NOT NULL,
+    PRIMARY KEY (snap_id, day)
+);
+
+CREATE INDEX daily_vote_counts_day ON daily_vote_counts (day);
+
+CREATE FUNCTION update_daily_vote_counts() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE daily_vote_counts
+        SET total_votes = total_votes - 1,
+            positive_votes = positive_votes - OLD.vote_up::INT
+        WHERE snap_id = OLD.snap_id
+        AND day = (OLD.created AT TIME ZONE 'UTC')::DATE;
+    END IF;
+
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        INSERT INTO daily_vote_counts (snap_id, day, total_votes, positive_votes)
+        VALUES (NEW.snap_id, (NEW.created AT TIME ZONE 'UTC')::DATE, 1, NEW.vote_up::INT)
+        ON CONFLICT (snap_id, day) DO UPDATE
+        SET total_votes = daily_vote_counts.total_votes + 1,
+            positive_votes = daily_vote_counts.positive_votes + EXCLUDED.positive_votes;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Backfill from the existing votes before the trigger starts tracking new writes
+LOCK TABLE votes IN SHARE MODE;
+
+INSERT INTO daily_vote_counts (snap_id, day, total_votes, positive_votes)
+SELECT
+    snap_id,
+    (created AT TIME ZONE 'UTC')::DATE,
+    COUNT(*),
+    COUNT(*) FILTER (WHERE vote_up)
+FROM votes
+GROUP BY snap_id, (created AT TIME ZONE 'UTC')::DATE;
+
+CREATE TRIGGER votes_daily_vote_counts
+AFTER INSERT OR UPDATE OR DELETE ON votes
+FOR EACH ROW EXECUTE FUNCTION update_daily_vote_counts();
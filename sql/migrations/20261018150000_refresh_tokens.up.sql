@@ -0,0 +1,12 @@
+-- Refresh tokens are exchanged by clients for new access tokens without needing to authenticate
+-- again. Only a hash of each token is stored and each token can be used once.
+
+CREATE TABLE refresh_tokens (
+    token_hash CHAR(64) PRIMARY KEY, -- sha256 of the token
+    user_id_fk INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires TIMESTAMPTZ NOT NULL,
+    revoked TIMESTAMPTZ
+);
+
+CREATE INDEX idx_refresh_tokens_user ON refresh_tokens (user_id_fk);
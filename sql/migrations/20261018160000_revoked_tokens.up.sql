@@ -0,0 +1,10 @@
+-- Access tokens can not be revoked individually, so clients whose tokens have been revoked (for
+-- example by deleting their account) are recorded here along with the time of the revocation.
+-- Any token issued to the client before then is rejected. Entries are no longer needed once every
+-- token they cover has expired.
+
+CREATE TABLE revoked_tokens (
+    client_hash CHAR(64) PRIMARY KEY, -- sha256([$user:$machineId])
+    revoked TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,264 @@
+//! Keeping the caches of multiple replicas in sync using Postgres LISTEN/NOTIFY
+//!
+//! Each replica evicts stale entries from its own caches as soon as it makes a change and then
+//! publishes what it evicted so that every other replica can do the same. Replicas receive
+//! their own notifications as well, which is harmless as evicting an entry twice has no effect.
+//!
+//! Access token revocations are shared on the same channel, see [Revocations](super::Revocations).
+use crate::{
+    cache::{chart_prefix, vote_summary_prefix, Caches},
+    conn,
+    db::{self, TokenRevocation, User},
+    Context,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{postgres::PgListener, PgConnection};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+
+/// The channel that cache invalidations are published on
+const INVALIDATION_CHANNEL: &str = "ratings_cache_invalidation";
+
+/// Postgres rejects notification payloads of 8000 bytes or more
+const MAX_PAYLOAD_LEN: usize = 7999;
+
+/// How long we wait before listening again if the listener fails
+const LISTEN_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A set of cached entries that have become stale.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Invalidation {
+    /// The votes for a snap have changed so its cached summaries are stale
+    Snap { snap_id: String },
+    /// The snaps in the given categories have changed so the charts for them are stale, along
+    /// with the charts across all categories
+    Charts { category_ids: Vec<i32> },
+    /// Every cached chart is stale
+    AllCharts,
+    /// Everything cached is stale
+    All,
+    /// The access tokens issued to a client have been revoked, which is applied to the
+    /// [Revocations](super::Revocations) rather than the [Caches]
+    TokensRevoked {
+        client_hash: String,
+        revocation: TokenRevocation,
+    },
+}
+
+impl Caches {
+    /// Evicts the entries covered by the given invalidations from the caches of this replica.
+    pub async fn apply(&self, invalidations: &[Invalidation]) {
+        for invalidation in invalidations {
+            match invalidation {
+                Invalidation::Snap { snap_id } => {
+                    self.vote_summaries
+                        .remove_prefix(&vote_summary_prefix(snap_id))
+                        .await;
+                }
+
+                Invalidation::Charts { category_ids } => {
+                    let categories = category_ids.iter().map(|&id| Some(id)).chain([None]);
+                    for category_id in categories {
+                        self.charts.remove_prefix(&chart_prefix(category_id)).await;
+                    }
+                }
+
+                Invalidation::AllCharts => self.charts.clear().await,
+
+                Invalidation::All => self.clear().await,
+
+                Invalidation::TokensRevoked { .. } => (),
+            }
+        }
+    }
+}
+
+/// Evicts the entries covered by the given invalidations from the caches of this replica and
+/// notifies the other replicas that they should do the same.
+///
+/// Failing to notify the other replicas is logged rather than returned as the change that made
+/// the entries stale has already been made: their entries will expire in time regardless.
+pub async fn invalidate(ctx: &Context, invalidations: &[Invalidation], conn: &mut PgConnection) {
+    apply(ctx, invalidations).await;
+
+    if let Err(e) = publish(invalidations, conn).await {
+        warn!("unable to notify other replicas of cache invalidations: {e}");
+    }
+}
+
+async fn publish(invalidations: &[Invalidation], conn: &mut PgConnection) -> Result<(), db::Error> {
+    let mut payload = serde_json::to_string(invalidations).expect("valid json");
+
+    // Rather than splitting large batches across multiple notifications we ask the other replicas
+    // to drop everything: large batches only come from rare admin actions. Revocations can not be
+    // recovered by dropping everything so they are always kept.
+    if payload.len() > MAX_PAYLOAD_LEN {
+        let kept: Vec<&Invalidation> = invalidations
+            .iter()
+            .filter(|i| matches!(i, Invalidation::TokensRevoked { .. }))
+            .chain([&Invalidation::All])
+            .collect();
+        payload = serde_json::to_string(&kept).expect("valid json");
+    }
+
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(INVALIDATION_CHANNEL)
+        .bind(payload)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Listens for the cache invalidations published by every replica, evicting the stale entries
+/// from the caches of this replica.
+///
+/// Any notifications sent while we are not listening are lost so the caches are cleared, and
+/// revocations are checked against the DB, until we are listening again.
+pub async fn listen_for_invalidations(ctx: Arc<Context>) {
+    loop {
+        if let Err(e) = listen(&ctx).await {
+            error!("unable to listen for cache invalidations: {e}");
+        }
+
+        ctx.revocations.desync();
+        ctx.caches.clear().await;
+        tokio::time::sleep(LISTEN_RETRY_INTERVAL).await;
+    }
+}
+
+/// Listens on a dedicated connection rather than one from the pool so that the listener never
+/// competes with requests for the limited number of pooled connections.
+async fn listen(ctx: &Context) -> Result<(), db::Error> {
+    let mut listener = PgListener::connect(&ctx.config.postgres_uri).await?;
+    listener.listen(INVALIDATION_CHANNEL).await?;
+
+    // Revocations made from here on are received as notifications
+    ctx.revocations
+        .sync(User::get_token_revocations(conn!()).await?);
+    info!("listening for cache invalidations");
+
+    loop {
+        // try_recv returns None when the connection is lost, in which case we start again so that
+        // the revocations are reloaded
+        let Some(notification) = listener.try_recv().await? else {
+            warn!("lost connection while listening for cache invalidations, clearing caches");
+            return Ok(());
+        };
+
+        match serde_json::from_str::<Vec<Invalidation>>(notification.payload()) {
+            Ok(invalidations) => apply(ctx, &invalidations).await,
+            Err(e) => warn!(
+                payload = notification.payload(),
+                "invalid cache invalidation: {e}"
+            ),
+        }
+    }
+}
+
+/// Applies the given invalidations to the caches and revocations of this replica.
+async fn apply(ctx: &Context, invalidations: &[Invalidation]) {
+    ctx.caches.apply(invalidations).await;
+    ctx.revocations.apply(invalidations);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{chart_key, vote_summary_key, Cache, CacheSettings},
+        db::{RevisionFilter, Timeframe, VoteSummary},
+        ratings::Chart,
+    };
+
+    fn caches() -> Caches {
+        let settings = CacheSettings {
+            size: 10,
+            ttl_secs: 60,
+        };
+
+        Caches {
+            vote_summaries: Cache::new(settings, true),
+            charts: Cache::new(settings, true),
+            snap_names: Cache::new(settings, true),
+        }
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn published_invalidations_are_received() -> Result<(), db::Error> {
+        let mut listener = PgListener::connect(&crate::Config::load()?.postgres_uri).await?;
+        listener.listen(INVALIDATION_CHANNEL).await?;
+
+        let invalidations = [Invalidation::Snap {
+            snap_id: "foo".to_string(),
+        }];
+        publish(&invalidations, crate::conn!()).await?;
+
+        let notification = listener.recv().await?;
+        let received: Vec<Invalidation> = serde_json::from_str(notification.payload()).unwrap();
+        assert_eq!(received, invalidations);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalidations_round_trip_through_json() {
+        let invalidations = vec![
+            Invalidation::Snap {
+                snap_id: "foo".to_string(),
+            },
+            Invalidation::Charts {
+                category_ids: vec![1, 2],
+            },
+            Invalidation::AllCharts,
+            Invalidation::All,
+        ];
+
+        let json = serde_json::to_string(&invalidations).unwrap();
+        let parsed: Vec<Invalidation> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed, invalidations);
+    }
+
+    #[tokio::test]
+    async fn applying_invalidations_evicts_only_stale_entries() {
+        let caches = caches();
+        for snap_id in ["foo", "bar"] {
+            let key = vote_summary_key(snap_id, RevisionFilter::All, None);
+            caches
+                .vote_summaries
+                .insert(key, VoteSummary::empty(snap_id))
+                .await;
+        }
+        for category_id in [None, Some(1), Some(2)] {
+            let key = chart_key(category_id, Timeframe::Week, None);
+            let chart = Chart {
+                timeframe: Timeframe::Week,
+                data: Vec::new(),
+            };
+            caches.charts.insert(key, chart).await;
+        }
+
+        caches
+            .apply(&[
+                Invalidation::Snap {
+                    snap_id: "foo".to_string(),
+                },
+                Invalidation::Charts {
+                    category_ids: vec![1],
+                },
+            ])
+            .await;
+
+        let summary = |snap_id| vote_summary_key(snap_id, RevisionFilter::All, None);
+        assert!(caches.vote_summaries.get(&summary("foo")).await.is_none());
+        assert!(caches.vote_summaries.get(&summary("bar")).await.is_some());
+
+        let chart = |category_id| chart_key(category_id, Timeframe::Week, None);
+        assert!(caches.charts.get(&chart(None)).await.is_none());
+        assert!(caches.charts.get(&chart(Some(1))).await.is_none());
+        assert!(caches.charts.get(&chart(Some(2))).await.is_some());
+    }
+}
@@ -0,0 +1,321 @@
+//! In-memory caching of query results, sized and enabled at runtime through [Config]
+use crate::{
+    config::Config,
+    db::{DateRange, RevisionFilter, Timeframe, VoteSummary},
+    metrics::CacheStats,
+    ratings::Chart,
+};
+use cached::{Cached, TimedSizedCache};
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use tokio::sync::Mutex;
+
+mod invalidation;
+mod revocations;
+
+pub use invalidation::{invalidate, listen_for_invalidations, Invalidation};
+pub use revocations::Revocations;
+
+/// The size and time to live for one kind of cached value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSettings {
+    /// The maximum number of entries held, with the least recently used being evicted first
+    pub size: usize,
+    /// How long in seconds an entry is used before it is discarded
+    pub ttl_secs: u64,
+}
+
+impl CacheSettings {
+    /// Checks that the cache is able to hold at least one entry for a non-zero amount of time.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size == 0 || self.ttl_secs == 0 {
+            return Err("cache sizes and ttls must be greater than zero".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// A size bounded cache of values with a time to live, keyed on strings. A disabled cache holds
+/// nothing and passes every lookup through to the underlying query.
+pub struct Cache<V> {
+    inner: Option<Mutex<TimedSizedCache<String, V>>>,
+    /// Bumped whenever values are removed, so that queries which started before the removal do
+    /// not cache their now stale results
+    generation: AtomicU64,
+}
+
+impl<V: Clone> Cache<V> {
+    pub fn new(settings: CacheSettings, enabled: bool) -> Self {
+        let inner = enabled.then(|| {
+            Mutex::new(TimedSizedCache::with_size_and_lifespan(
+                settings.size,
+                settings.ttl_secs,
+            ))
+        });
+
+        Self {
+            inner,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// A cache that never holds anything.
+    pub fn disabled() -> Self {
+        Self {
+            inner: None,
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Looks up a cached value.
+    pub async fn get(&self, key: &str) -> Option<V> {
+        let mut cache = self.inner.as_ref()?.lock().await;
+
+        cache.cache_get(key).cloned()
+    }
+
+    /// Stores a value, replacing any existing value for the key.
+    pub async fn insert(&self, key: String, value: V) {
+        if let Some(inner) = &self.inner {
+            inner.lock().await.cache_set(key, value);
+        }
+    }
+
+    /// Returns the cached value for the key if there is one, otherwise runs the given query and
+    /// caches the result if it succeeds. The lock is not held while the query runs so concurrent
+    /// misses for the same key may each run it. If anything is removed from the cache while the
+    /// query runs then its result may already be stale, so it is returned without being cached.
+    pub async fn get_or_try_insert_with<F, Fut, E>(&self, key: String, f: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(value) = self.get(&key).await {
+            return Ok(value);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let value = f().await?;
+
+        if let Some(inner) = &self.inner {
+            // The generation is only bumped with the lock held so it can not change before we set
+            let mut cache = inner.lock().await;
+            if self.generation.load(Ordering::SeqCst) == generation {
+                cache.cache_set(key, value.clone());
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Removes every value whose key starts with the given prefix.
+    pub async fn remove_prefix(&self, prefix: &str) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+
+        let mut cache = inner.lock().await;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let keys: Vec<String> = cache
+            .key_order()
+            .filter(|k| k.starts_with(prefix))
+            .cloned()
+            .collect();
+
+        for key in keys {
+            cache.cache_remove(&key);
+        }
+    }
+
+    /// Removes all cached values.
+    pub async fn clear(&self) {
+        if let Some(inner) = &self.inner {
+            let mut cache = inner.lock().await;
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            cache.cache_clear();
+        }
+    }
+
+    /// The hit and miss counts for this cache, which are always zero when it is disabled.
+    pub async fn stats(&self) -> CacheStats {
+        let Some(inner) = &self.inner else {
+            return CacheStats::default();
+        };
+
+        let cache = inner.lock().await;
+
+        CacheStats {
+            hits: cache.cache_hits().unwrap_or_default(),
+            misses: cache.cache_misses().unwrap_or_default(),
+        }
+    }
+}
+
+/// The caches shared by the services, one per kind of cached value.
+pub struct Caches {
+    /// Vote summaries for individual snaps, keyed by [vote_summary_key]
+    pub vote_summaries: Cache<VoteSummary>,
+    /// Fully ranked charts, keyed by [chart_key]
+    pub charts: Cache<Chart>,
+    /// Snap names, keyed by snap id
+    pub snap_names: Cache<String>,
+}
+
+impl Caches {
+    pub fn new(config: &Config) -> Self {
+        let enabled = config.cache_enabled;
+
+        Self {
+            vote_summaries: Cache::new(config.vote_summary_cache(), enabled),
+            charts: Cache::new(config.chart_cache(), enabled),
+            snap_names: Cache::new(config.snap_name_cache(), enabled),
+        }
+    }
+
+    /// Removes everything from all of the caches.
+    pub async fn clear(&self) {
+        self.vote_summaries.clear().await;
+        self.charts.clear().await;
+        self.snap_names.clear().await;
+    }
+
+    /// The hit and miss counts for each cache, labelled by the kind of value it holds.
+    pub async fn stats(&self) -> [(&'static str, CacheStats); 3] {
+        [
+            ("vote_summary", self.vote_summaries.stats().await),
+            ("chart", self.charts.stats().await),
+            ("snap_name", self.snap_names.stats().await),
+        ]
+    }
+}
+
+/// The key for a cached vote summary, which starts with [vote_summary_prefix] for the snap.
+pub fn vote_summary_key(
+    snap_id: &str,
+    revisions: RevisionFilter,
+    range: Option<DateRange>,
+) -> String {
+    format!("{}{revisions:?}:{range:?}", vote_summary_prefix(snap_id))
+}
+
+/// The prefix shared by the keys of all cached vote summaries for a snap.
+pub fn vote_summary_prefix(snap_id: &str) -> String {
+    format!("{snap_id}:")
+}
+
+/// The key for a cached chart, which starts with [chart_prefix] for the category.
+pub fn chart_key(
+    category_id: Option<i32>,
+    timeframe: Timeframe,
+    range: Option<DateRange>,
+) -> String {
+    format!("{}{timeframe:?}:{range:?}", chart_prefix(category_id))
+}
+
+/// The prefix shared by the keys of all cached charts for a category, or for the charts across all
+/// categories when there is no category.
+pub fn chart_prefix(category_id: Option<i32>) -> String {
+    format!("{category_id:?}:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> Cache<u32> {
+        Cache::new(
+            CacheSettings {
+                size: 2,
+                ttl_secs: 60,
+            },
+            true,
+        )
+    }
+
+    #[tokio::test]
+    async fn values_are_cached_until_removed() {
+        let cache = cache();
+        let v: Result<u32, ()> = cache
+            .get_or_try_insert_with("a".into(), || async { Ok(1) })
+            .await;
+        assert_eq!(v, Ok(1));
+
+        let v: Result<u32, ()> = cache
+            .get_or_try_insert_with("a".into(), || async { Ok(2) })
+            .await;
+        assert_eq!(v, Ok(1), "the cached value should be used");
+
+        cache.remove_prefix("a").await;
+        assert_eq!(cache.get("a").await, None);
+        assert_eq!(cache.stats().await, CacheStats { hits: 1, misses: 2 });
+    }
+
+    #[tokio::test]
+    async fn values_are_not_cached_if_removed_while_being_queried() {
+        let cache = cache();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        let fill = cache.get_or_try_insert_with("a".into(), || rx);
+        let remove = async {
+            cache.remove_prefix("a").await;
+            tx.send(1).unwrap();
+        };
+        let (v, ()) = tokio::join!(fill, remove);
+
+        assert_eq!(v, Ok(1));
+        assert_eq!(
+            cache.get("a").await,
+            None,
+            "the stale value should not be cached"
+        );
+
+        let v: Result<u32, ()> = cache
+            .get_or_try_insert_with("a".into(), || async { Ok(2) })
+            .await;
+        assert_eq!(v, Ok(2));
+        assert_eq!(cache.get("a").await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = cache();
+        let v = cache
+            .get_or_try_insert_with("a".into(), || async { Err("boom") })
+            .await;
+        assert_eq!(v, Err("boom"));
+        assert_eq!(cache.get("a").await, None);
+    }
+
+    #[tokio::test]
+    async fn the_least_recently_used_value_is_evicted() {
+        let cache = cache();
+        cache.insert("a".into(), 1).await;
+        cache.insert("b".into(), 2).await;
+        cache.get("a").await;
+        cache.insert("c".into(), 3).await;
+
+        assert_eq!(cache.get("a").await, Some(1));
+        assert_eq!(cache.get("b").await, None);
+        assert_eq!(cache.get("c").await, Some(3));
+    }
+
+    #[tokio::test]
+    async fn disabled_caches_hold_nothing() {
+        let cache: Cache<u32> = Cache::disabled();
+        cache.insert("a".into(), 1).await;
+
+        assert_eq!(cache.get("a").await, None);
+        assert_eq!(cache.stats().await, CacheStats::default());
+    }
+
+    #[test]
+    fn chart_prefixes_do_not_overlap() {
+        let key = chart_key(Some(12), Timeframe::Week, None);
+
+        assert!(key.starts_with(&chart_prefix(Some(12))));
+        assert!(!key.starts_with(&chart_prefix(Some(1))));
+    }
+}
@@ -0,0 +1,118 @@
+//! Access token revocations held in memory so that authenticating a request does not need a DB
+//! query.
+//!
+//! Every revocation is loaded from the DB when we start listening for cache invalidations, after
+//! which new revocations arrive as [Invalidation::TokensRevoked]. While we are not listening the
+//! revocations may be out of date, so the DB has to be checked instead.
+use crate::{
+    cache::Invalidation,
+    db::{ClientHash, TokenRevocation},
+};
+use std::{collections::HashMap, sync::RwLock};
+use time::OffsetDateTime;
+
+#[derive(Debug, Default)]
+pub struct Revocations {
+    /// The current revocation for each client, or None while we are not in sync with the DB
+    revoked: RwLock<Option<HashMap<ClientHash, TokenRevocation>>>,
+}
+
+impl Revocations {
+    /// Whether or not a token issued to the given [ClientHash] at `issued_at` has been revoked,
+    /// or None if we are not in sync with the DB and it needs to be checked directly.
+    pub fn is_revoked(&self, client_hash: &str, issued_at: OffsetDateTime) -> Option<bool> {
+        let revoked = self.revoked.read().expect("revocations lock poisoned");
+        let revocation = revoked.as_ref()?.get(client_hash);
+
+        Some(revocation.is_some_and(|r| r.revoked >= issued_at))
+    }
+
+    /// Replaces the revocations held in memory with those loaded from the DB.
+    pub fn sync(&self, revocations: HashMap<ClientHash, TokenRevocation>) {
+        *self.revoked.write().expect("revocations lock poisoned") = Some(revocations);
+    }
+
+    /// Marks the revocations held in memory as out of date, until they are next synced.
+    pub fn desync(&self) {
+        *self.revoked.write().expect("revocations lock poisoned") = None;
+    }
+
+    /// Records the revocations from the given invalidations, dropping any revocations whose
+    /// tokens have all since expired.
+    pub fn apply(&self, invalidations: &[Invalidation]) {
+        let mut revoked = self.revoked.write().expect("revocations lock poisoned");
+        let Some(revoked) = revoked.as_mut() else {
+            return;
+        };
+
+        for invalidation in invalidations {
+            if let Invalidation::TokensRevoked {
+                client_hash,
+                revocation,
+            } = invalidation
+            {
+                let now = OffsetDateTime::now_utc();
+                revoked.retain(|_, r| r.expires > now);
+                revoked.insert(client_hash.clone(), *revocation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Duration;
+
+    fn revoked(client_hash: &str, revocation: TokenRevocation) -> Invalidation {
+        Invalidation::TokensRevoked {
+            client_hash: client_hash.to_string(),
+            revocation,
+        }
+    }
+
+    #[test]
+    fn revocations_are_only_known_while_in_sync() {
+        let revocations = Revocations::default();
+        let revocation = TokenRevocation::now(Duration::hours(1));
+        let before = revocation.revoked - Duration::seconds(1);
+        let after = revocation.revoked + Duration::milliseconds(1);
+
+        assert_eq!(revocations.is_revoked("a", before), None);
+        revocations.apply(&[revoked("a", revocation)]);
+        assert_eq!(revocations.is_revoked("a", before), None);
+
+        revocations.sync(HashMap::new());
+        revocations.apply(&[revoked("a", revocation)]);
+        assert_eq!(revocations.is_revoked("a", before), Some(true));
+        assert_eq!(revocations.is_revoked("a", after), Some(false));
+        assert_eq!(revocations.is_revoked("b", before), Some(false));
+
+        revocations.desync();
+        assert_eq!(revocations.is_revoked("a", before), None);
+    }
+
+    #[test]
+    fn revocations_can_be_published() {
+        let invalidation = revoked("a", TokenRevocation::now(Duration::hours(1)));
+        let json = serde_json::to_string(&[&invalidation]).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Vec<Invalidation>>(&json).unwrap(),
+            [invalidation]
+        );
+    }
+
+    #[test]
+    fn expired_revocations_are_dropped() {
+        let revocations = Revocations::default();
+        let expired = TokenRevocation::now(Duration::hours(-1));
+        let issued_at = expired.revoked - Duration::hours(2);
+        revocations.sync(HashMap::from([("a".to_string(), expired)]));
+
+        revocations.apply(&[revoked("b", TokenRevocation::now(Duration::hours(1)))]);
+
+        assert_eq!(revocations.is_revoked("a", issued_at), Some(false));
+        assert_eq!(revocations.is_revoked("b", issued_at), Some(true));
+    }
+}
@@ -1,7 +1,14 @@
 //! Utility functions and definitions for configuring the service.
+use crate::{
+    cache::CacheSettings,
+    db::VoteDecay,
+    middleware::AdminCredentials,
+    ratings::{BandThresholds, RankingStrategy, RankingStrategyKind, DEFAULT_Z_SCORE},
+};
 use dotenvy::dotenv;
 use secrecy::SecretString;
 use serde::Deserialize;
+use time::Duration;
 
 /// Configuration for the general app center ratings backend service.
 #[derive(Deserialize, Debug, Clone)]
@@ -12,14 +19,98 @@ pub struct Config {
     pub port: u16,
     /// The URI of the postgres database
     pub postgres_uri: String,
-    /// The JWT secret value
-    pub jwt_secret: SecretString,
+    /// The base64 encoded secret used to sign and verify tokens without a key id
+    pub jwt_secret: Option<SecretString>,
+    /// Additional JWT keys as a comma separated list of `kid:secret` pairs
+    pub jwt_keys: Option<SecretString>,
+    /// A directory of additional JWT keys, with a file for each key named after its key id. Each
+    /// file holds either a base64 encoded HMAC secret or a PEM encoded RSA or Ed25519 private key
+    pub jwt_keys_dir: Option<String>,
+    /// The id of the key used to sign new tokens, the jwt secret is used if this is not set
+    pub jwt_active_kid: Option<String>,
     /// The base URI for snapcraft.io
     pub snapcraft_io_uri: String,
     /// The path to the tls keychain
     pub tls_keychain_path: Option<String>,
     /// The path to the tls private key
     pub tls_key_path: Option<String>,
+    /// The port to serve Prometheus metrics on, if any
+    pub metrics_port: Option<u16>,
+    /// The port to serve the JWKS document for our asymmetric JWT keys on
+    #[serde(default = "default_jwks_port")]
+    pub jwks_port: u16,
+    /// The username for calling the admin service with basic credentials, which are only accepted
+    /// when this and the password are set
+    pub admin_user: Option<String>,
+    /// The password for the admin service
+    pub admin_password: Option<SecretString>,
+    /// The raw rating above which a snap is rated very good
+    #[serde(default = "default_band_good_upper")]
+    pub band_good_upper: f64,
+    /// The raw rating above which a snap is rated good
+    #[serde(default = "default_band_neutral_upper")]
+    pub band_neutral_upper: f64,
+    /// The raw rating above which a snap is rated neutral
+    #[serde(default = "default_band_poor_upper")]
+    pub band_poor_upper: f64,
+    /// The raw rating above which a snap is rated poor rather than very poor
+    #[serde(default = "default_band_very_poor_upper")]
+    pub band_very_poor_upper: f64,
+    /// The number of votes a snap needs before it is given a rating band
+    #[serde(default = "default_band_min_votes")]
+    pub band_min_votes: i64,
+    /// The strategy used to calculate raw ratings for ranking snaps
+    #[serde(default)]
+    pub ranking_strategy: RankingStrategyKind,
+    /// The z-score used by the wilson ranking strategy
+    #[serde(default = "default_ranking_z_score")]
+    pub ranking_z_score: f64,
+    /// The prior positive vote ratio used by the bayesian ranking strategy
+    #[serde(default = "default_ranking_prior_ratio")]
+    pub ranking_prior_ratio: f64,
+    /// The weight, in votes, given to the prior by the bayesian ranking strategy
+    #[serde(default = "default_ranking_prior_votes")]
+    pub ranking_prior_votes: f64,
+    /// The half life in days of the weight given to votes when ranking snaps, if votes should
+    /// decay over time
+    pub vote_decay_half_life_days: Option<f64>,
+    /// How long in hours snap metadata from snapcraft.io is used before it is refreshed
+    #[serde(default = "default_snap_metadata_max_age_hours")]
+    pub snap_metadata_max_age_hours: u32,
+    /// How long in hours the categories for a snap are kept before being re-synced with
+    /// snapcraft.io in the background
+    #[serde(default = "default_category_max_age_hours")]
+    pub category_max_age_hours: u32,
+    /// The maximum number of entries returned in a single page of a chart
+    #[serde(default = "default_chart_max_page_size")]
+    pub chart_max_page_size: u32,
+    /// How long in minutes an access token is valid for
+    #[serde(default = "default_access_token_lifetime_minutes")]
+    pub access_token_lifetime_minutes: u32,
+    /// How long in days a refresh token can be exchanged for a new access token
+    #[serde(default = "default_refresh_token_lifetime_days")]
+    pub refresh_token_lifetime_days: u32,
+    /// Whether vote summaries, charts and snap names are cached in memory
+    #[serde(default = "default_cache_enabled")]
+    pub cache_enabled: bool,
+    /// The maximum number of cached vote summaries
+    #[serde(default = "default_vote_summary_cache_size")]
+    pub vote_summary_cache_size: usize,
+    /// How long in seconds a vote summary is cached for
+    #[serde(default = "default_cache_ttl_secs")]
+    pub vote_summary_cache_ttl_secs: u64,
+    /// The maximum number of cached charts
+    #[serde(default = "default_chart_cache_size")]
+    pub chart_cache_size: usize,
+    /// How long in seconds a chart is cached for
+    #[serde(default = "default_cache_ttl_secs")]
+    pub chart_cache_ttl_secs: u64,
+    /// The maximum number of cached snap names
+    #[serde(default = "default_snap_name_cache_size")]
+    pub snap_name_cache_size: usize,
+    /// How long in seconds a snap name is cached for
+    #[serde(default = "default_snap_name_cache_ttl_secs")]
+    pub snap_name_cache_ttl_secs: u64,
 }
 
 impl Config {
@@ -27,7 +118,132 @@ impl Config {
     pub fn load() -> envy::Result<Config> {
         dotenv().ok();
 
-        envy::prefixed("APP_").from_env::<Config>()
+        let config = envy::prefixed("APP_").from_env::<Config>()?;
+        config
+            .band_thresholds()
+            .validate()
+            .map_err(envy::Error::Custom)?;
+        config
+            .ranking_strategy()
+            .validate()
+            .map_err(envy::Error::Custom)?;
+        config
+            .vote_decay()
+            .validate()
+            .map_err(envy::Error::Custom)?;
+
+        for cache in [
+            config.vote_summary_cache(),
+            config.chart_cache(),
+            config.snap_name_cache(),
+        ] {
+            cache.validate().map_err(envy::Error::Custom)?;
+        }
+
+        if config.access_token_lifetime_minutes == 0 || config.refresh_token_lifetime_days == 0 {
+            return Err(envy::Error::Custom(
+                "token lifetimes must be greater than zero".to_string(),
+            ));
+        }
+
+        if config.chart_max_page_size == 0 {
+            return Err(envy::Error::Custom(
+                "the chart max page size must be greater than zero".to_string(),
+            ));
+        }
+
+        if config.admin_user.is_some() != config.admin_password.is_some() {
+            return Err(envy::Error::Custom(
+                "both the admin user and password must be provided, or neither".to_string(),
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// The [`BandThresholds`] used to convert raw ratings into ratings bands
+    pub fn band_thresholds(&self) -> BandThresholds {
+        BandThresholds {
+            good_upper: self.band_good_upper,
+            neutral_upper: self.band_neutral_upper,
+            poor_upper: self.band_poor_upper,
+            very_poor_upper: self.band_very_poor_upper,
+            min_votes: self.band_min_votes,
+        }
+    }
+
+    /// The [`RankingStrategy`] used to calculate raw ratings for snaps
+    pub fn ranking_strategy(&self) -> RankingStrategy {
+        match self.ranking_strategy {
+            RankingStrategyKind::Wilson => RankingStrategy::Wilson {
+                z_score: self.ranking_z_score,
+            },
+            RankingStrategyKind::Bayesian => RankingStrategy::Bayesian {
+                prior_ratio: self.ranking_prior_ratio,
+                prior_votes: self.ranking_prior_votes,
+            },
+            RankingStrategyKind::Ratio => RankingStrategy::Ratio,
+        }
+    }
+
+    /// The [`VoteDecay`] used to weight votes by their age
+    pub fn vote_decay(&self) -> VoteDecay {
+        match self.vote_decay_half_life_days {
+            Some(half_life_days) => VoteDecay::Exponential { half_life_days },
+            None => VoteDecay::None,
+        }
+    }
+
+    /// How long snap metadata from snapcraft.io is used before it is refreshed
+    pub fn snap_metadata_max_age(&self) -> Duration {
+        Duration::hours(self.snap_metadata_max_age_hours as i64)
+    }
+
+    /// How long the categories for a snap are kept before being re-synced with snapcraft.io
+    pub fn category_max_age(&self) -> Duration {
+        Duration::hours(self.category_max_age_hours as i64)
+    }
+
+    /// How long an access token is valid for
+    pub fn access_token_lifetime(&self) -> Duration {
+        Duration::minutes(self.access_token_lifetime_minutes as i64)
+    }
+
+    /// How long a refresh token can be exchanged for a new access token
+    pub fn refresh_token_lifetime(&self) -> Duration {
+        Duration::days(self.refresh_token_lifetime_days as i64)
+    }
+
+    /// The [`CacheSettings`] for vote summaries
+    pub fn vote_summary_cache(&self) -> CacheSettings {
+        CacheSettings {
+            size: self.vote_summary_cache_size,
+            ttl_secs: self.vote_summary_cache_ttl_secs,
+        }
+    }
+
+    /// The [`CacheSettings`] for charts
+    pub fn chart_cache(&self) -> CacheSettings {
+        CacheSettings {
+            size: self.chart_cache_size,
+            ttl_secs: self.chart_cache_ttl_secs,
+        }
+    }
+
+    /// The [`CacheSettings`] for snap names
+    pub fn snap_name_cache(&self) -> CacheSettings {
+        CacheSettings {
+            size: self.snap_name_cache_size,
+            ttl_secs: self.snap_name_cache_ttl_secs,
+        }
+    }
+
+    /// The [`AdminCredentials`] accepted by the admin service, if they are configured
+    pub fn admin_credentials(&self) -> Option<AdminCredentials> {
+        match (&self.admin_user, &self.admin_password) {
+            (Some(user), Some(password)) => Some(AdminCredentials::new(user, password)),
+            _ => None,
+        }
     }
 
     /// Return a [`String`] representing the socket to run the service on
@@ -37,3 +253,83 @@ impl Config {
         format!("{host}:{port}")
     }
 }
+
+fn default_band_good_upper() -> f64 {
+    BandThresholds::default().good_upper
+}
+
+fn default_band_neutral_upper() -> f64 {
+    BandThresholds::default().neutral_upper
+}
+
+fn default_band_poor_upper() -> f64 {
+    BandThresholds::default().poor_upper
+}
+
+fn default_band_very_poor_upper() -> f64 {
+    BandThresholds::default().very_poor_upper
+}
+
+fn default_band_min_votes() -> i64 {
+    BandThresholds::default().min_votes
+}
+
+fn default_ranking_z_score() -> f64 {
+    DEFAULT_Z_SCORE
+}
+
+fn default_ranking_prior_ratio() -> f64 {
+    0.5
+}
+
+fn default_ranking_prior_votes() -> f64 {
+    10.0
+}
+
+fn default_jwks_port() -> u16 {
+    8081
+}
+
+fn default_snap_metadata_max_age_hours() -> u32 {
+    24
+}
+
+fn default_category_max_age_hours() -> u32 {
+    24 * 7
+}
+
+fn default_chart_max_page_size() -> u32 {
+    100
+}
+
+fn default_access_token_lifetime_minutes() -> u32 {
+    24 * 60
+}
+
+fn default_refresh_token_lifetime_days() -> u32 {
+    30
+}
+
+fn default_cache_enabled() -> bool {
+    true
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_vote_summary_cache_size() -> usize {
+    10_000
+}
+
+fn default_chart_cache_size() -> usize {
+    1_000
+}
+
+fn default_snap_name_cache_size() -> usize {
+    10_000
+}
+
+fn default_snap_name_cache_ttl_secs() -> u64 {
+    60 * 60
+}
@@ -1,15 +1,30 @@
 //! Application level context & state
 use crate::{
+    cache::{Caches, Revocations},
     config::Config,
-    jwt::{Error, JwtEncoder},
+    db::VoteDecay,
+    jwt::{Error, JwtEncoder, JwtKeySet, JwtVerifier},
+    ratings::{BandThresholds, RankingStrategy},
 };
 use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::sync::{Mutex, Notify};
 
 pub struct Context {
     pub config: Config,
+    pub jwt_keys: JwtKeySet,
     pub jwt_encoder: JwtEncoder,
+    pub jwt_verifier: JwtVerifier,
     pub http_client: reqwest::Client,
+    pub caches: Caches,
+    /// The access tokens revoked across every replica, shared with the auth middleware
+    pub revocations: Arc<Revocations>,
+
+    /// How votes are combined into a raw rating for each snap
+    pub ranking_strategy: RankingStrategy,
+    /// How raw ratings are mapped onto ratings bands
+    pub band_thresholds: BandThresholds,
+    /// How much weight older votes carry
+    pub vote_decay: VoteDecay,
 
     /// In progress category updates that we need to block on
     pub category_updates: Mutex<HashMap<String, Arc<Notify>>>,
@@ -17,14 +32,27 @@ pub struct Context {
 
 impl Context {
     pub fn new(config: Config) -> Result<Self, Error> {
-        let jwt_encoder = JwtEncoder::from_secret(&config.jwt_secret)?;
+        let jwt_keys = JwtKeySet::from_config(&config)?;
+        let jwt_encoder = JwtEncoder::new(&jwt_keys, config.access_token_lifetime())?;
+        let jwt_verifier = JwtVerifier::new(&jwt_keys)?;
+        let caches = Caches::new(&config);
+        let ranking_strategy = config.ranking_strategy();
+        let band_thresholds = config.band_thresholds();
+        let vote_decay = config.vote_decay();
 
         Ok(Self {
             config,
+            jwt_keys,
             jwt_encoder,
+            jwt_verifier,
             http_client: reqwest::Client::builder()
                 .pool_idle_timeout(Duration::from_secs(5))
                 .build()?,
+            caches,
+            revocations: Default::default(),
+            ranking_strategy,
+            band_thresholds,
+            vote_decay,
             category_updates: Default::default(),
         })
     }
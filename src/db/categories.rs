@@ -1,41 +1,101 @@
-use crate::db::Result;
-use sqlx::{PgConnection, Postgres, QueryBuilder};
-
-#[derive(
-    Debug,
-    Clone,
-    Copy,
-    PartialEq,
-    Eq,
-    Hash,
-    sqlx::Type,
-    strum::EnumString,
-    strum::Display,
-    strum::FromRepr,
-)]
-#[repr(i32)]
-#[strum(serialize_all = "kebab-case")]
-pub enum Category {
-    ArtAndDesign = 0,
-    BooksAndReference = 1,
-    Development = 2,
-    DevicesAndIot = 3,
-    Education = 4,
-    Entertainment = 5,
-    Featured = 6,
-    Finance = 7,
-    Games = 8,
-    HealthAndFitness = 9,
-    MusicAndAudio = 10,
-    NewsAndWeather = 11,
-    Personalisation = 12,
-    PhotoAndVideo = 13,
-    Productivity = 14,
-    Science = 15,
-    Security = 16,
-    ServerAndCloud = 17,
-    Social = 18,
-    Utilities = 19,
+use crate::db::{Error, Result};
+use sqlx::{prelude::FromRow, Acquire, PgConnection, Postgres, QueryBuilder};
+use time::Duration;
+use tracing::error;
+
+/// A category that snaps are listed under in the store.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromRow)]
+pub struct Category {
+    /// The ID of the category
+    pub id: i32,
+    /// The identifier used for the category by snapcraft.io, e.g. `art-and-design`
+    pub slug: String,
+    /// The human readable name of the category
+    pub name: String,
+}
+
+impl Category {
+    /// All known categories, ordered by their ID.
+    pub async fn get_all(conn: &mut PgConnection) -> Result<Vec<Self>> {
+        let categories = sqlx::query_as("SELECT id, slug, name FROM categories ORDER BY id;")
+            .fetch_all(conn)
+            .await?;
+
+        Ok(categories)
+    }
+
+    /// Looks up a category by its ID.
+    pub async fn get_by_id(id: i32, conn: &mut PgConnection) -> Result<Option<Self>> {
+        let category = sqlx::query_as("SELECT id, slug, name FROM categories WHERE id = $1;")
+            .bind(id)
+            .fetch_optional(conn)
+            .await?;
+
+        Ok(category)
+    }
+
+    /// Looks up a category by its slug.
+    pub async fn get_by_slug(slug: &str, conn: &mut PgConnection) -> Result<Option<Self>> {
+        let category = sqlx::query_as("SELECT id, slug, name FROM categories WHERE slug = $1;")
+            .bind(slug)
+            .fetch_optional(conn)
+            .await?;
+
+        Ok(category)
+    }
+
+    /// Looks up any of the given slugs that correspond to known categories, ordered by their ID.
+    pub async fn get_by_slugs(slugs: &[String], conn: &mut PgConnection) -> Result<Vec<Self>> {
+        let categories = sqlx::query_as(
+            "SELECT id, slug, name FROM categories WHERE slug = ANY($1) ORDER BY id;",
+        )
+        .bind(slugs)
+        .fetch_all(conn)
+        .await?;
+
+        Ok(categories)
+    }
+
+    /// Adds a new category, returning `None` if a category with the same slug already exists.
+    ///
+    /// Any snaps that were previously seen with this category as an unknown slug are marked for
+    /// their categories to be refreshed.
+    pub async fn create(slug: &str, name: &str, conn: &mut PgConnection) -> Result<Option<Self>> {
+        let mut tx = conn.begin().await?;
+
+        let category: Option<Self> = sqlx::query_as(
+            r#"
+        INSERT INTO categories (slug, name)
+        VALUES ($1, $2)
+        ON CONFLICT (slug) DO NOTHING
+        RETURNING id, slug, name;
+        "#,
+        )
+        .bind(slug)
+        .bind(name)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToCreateCategory
+        })?;
+
+        if category.is_some() {
+            sqlx::query(
+                r#"
+            UPDATE snaps SET categories_refreshed = NULL
+            WHERE snap_id IN (SELECT snap_id FROM unknown_categories WHERE slug = $1);
+            "#,
+            )
+            .bind(slug)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(category)
+    }
 }
 
 pub async fn snap_has_categories(snap_id: &str, conn: &mut PgConnection) -> Result<bool> {
@@ -48,19 +108,145 @@ pub async fn snap_has_categories(snap_id: &str, conn: &mut PgConnection) -> Resu
     Ok(n_rows > 0)
 }
 
+/// The categories currently stored for the given snap, ordered by their ID.
+pub async fn categories_for_snap(snap_id: &str, conn: &mut PgConnection) -> Result<Vec<Category>> {
+    let categories = sqlx::query_as(
+        r#"
+        SELECT categories.id, categories.slug, categories.name
+        FROM snap_categories
+        JOIN categories ON categories.id = snap_categories.category
+        WHERE snap_categories.snap_id = $1
+        ORDER BY categories.id;
+        "#,
+    )
+    .bind(snap_id)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(categories)
+}
+
+/// Replaces the stored categories for a snap and records when they were refreshed, returning
+/// whether or not the categories changed as a result.
 pub async fn set_categories_for_snap(
     snap_id: &str,
-    categories: Vec<Category>,
+    mut categories: Vec<Category>,
+    conn: &mut PgConnection,
+) -> Result<bool> {
+    categories.sort_by_key(|c| c.id);
+    categories.dedup();
+
+    let mut tx = conn.begin().await?;
+
+    let existing = categories_for_snap(snap_id, &mut tx).await?;
+    let changed = existing != categories;
+
+    if changed {
+        sqlx::query("DELETE FROM snap_categories WHERE snap_id = $1;")
+            .bind(snap_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if !categories.is_empty() {
+            let mut query_builder: QueryBuilder<Postgres> =
+                QueryBuilder::new("INSERT INTO snap_categories(snap_id, category) ");
+
+            query_builder.push_values(categories, |mut b, category| {
+                b.push_bind(snap_id).push_bind(category.id);
+            });
+
+            query_builder.build().execute(&mut *tx).await?;
+        }
+    }
+
+    mark_categories_refreshed(snap_id, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok(changed)
+}
+
+/// Records that the categories for a snap were refreshed without changing them, so that it is
+/// not refreshed again until they are older than the configured maximum age.
+pub async fn mark_categories_refreshed(snap_id: &str, conn: &mut PgConnection) -> Result<()> {
+    sqlx::query("UPDATE snaps SET categories_refreshed = NOW() WHERE snap_id = $1;")
+        .bind(snap_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Records the category slugs from snapcraft.io that did not match any known category for a
+/// snap, replacing any that were previously recorded for it.
+pub async fn quarantine_unknown_categories(
+    snap_id: &str,
+    slugs: &[String],
     conn: &mut PgConnection,
 ) -> Result<()> {
-    let mut query_builder: QueryBuilder<Postgres> =
-        QueryBuilder::new("INSERT INTO snap_categories(snap_id, category) ");
+    let mut tx = conn.begin().await?;
+
+    sqlx::query("DELETE FROM unknown_categories WHERE snap_id = $1 AND NOT slug = ANY($2);")
+        .bind(snap_id)
+        .bind(slugs)
+        .execute(&mut *tx)
+        .await?;
 
-    query_builder.push_values(categories, |mut b, category| {
-        b.push_bind(snap_id).push_bind(category);
-    });
+    if !slugs.is_empty() {
+        sqlx::query(
+            r#"
+        INSERT INTO unknown_categories (snap_id, slug)
+        SELECT $1, slug FROM UNNEST($2::TEXT[]) AS slug
+        ON CONFLICT (snap_id, slug) DO UPDATE SET last_seen = NOW();
+        "#,
+        )
+        .bind(snap_id)
+        .bind(slugs)
+        .execute(&mut *tx)
+        .await?;
+    }
 
-    query_builder.build().execute(conn).await?;
+    tx.commit().await?;
 
     Ok(())
 }
+
+/// The unknown category slugs currently recorded for the given snap.
+pub async fn unknown_categories_for_snap(
+    snap_id: &str,
+    conn: &mut PgConnection,
+) -> Result<Vec<String>> {
+    let slugs: Vec<(String,)> =
+        sqlx::query_as("SELECT slug FROM unknown_categories WHERE snap_id = $1 ORDER BY slug;")
+            .bind(snap_id)
+            .fetch_all(conn)
+            .await?;
+
+    Ok(slugs.into_iter().map(|(slug,)| slug).collect())
+}
+
+/// The IDs of snaps with stored or unknown categories that have not been refreshed within the
+/// given age.
+pub async fn snaps_with_stale_categories(
+    max_age: Duration,
+    conn: &mut PgConnection,
+) -> Result<Vec<String>> {
+    let snap_ids: Vec<(String,)> = sqlx::query_as(
+        r#"
+        SELECT categorised.snap_id
+        FROM (
+            SELECT snap_id FROM snap_categories
+            UNION
+            SELECT snap_id FROM unknown_categories
+        ) AS categorised
+        LEFT JOIN snaps ON snaps.snap_id = categorised.snap_id
+        WHERE snaps.categories_refreshed IS NULL
+        OR snaps.categories_refreshed < NOW() - make_interval(secs => $1)
+        ORDER BY snaps.categories_refreshed NULLS FIRST;
+        "#,
+    )
+    .bind(max_age.as_seconds_f64())
+    .fetch_all(conn)
+    .await?;
+
+    Ok(snap_ids.into_iter().map(|(snap_id,)| snap_id).collect())
+}
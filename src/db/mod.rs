@@ -5,12 +5,23 @@ use tokio::sync::OnceCell;
 use tracing::info;
 
 mod categories;
+mod refresh_token;
+mod snap;
 mod user;
 mod vote;
 
-pub use categories::{set_categories_for_snap, snap_has_categories, Category};
-pub use user::User;
-pub use vote::{Timeframe, Vote, VoteSummary};
+pub use categories::{
+    categories_for_snap, mark_categories_refreshed, quarantine_unknown_categories,
+    set_categories_for_snap, snap_has_categories, snaps_with_stale_categories,
+    unknown_categories_for_snap, Category,
+};
+pub use refresh_token::{create_refresh_token, redeem_refresh_token};
+pub use snap::Snap;
+pub use user::{TokenRevocation, User};
+pub use vote::{
+    DateRange, RevisionFilter, RevisionVoteCounts, Timeframe, TrendingSummaries, Vote, VoteCursor,
+    VoteDecay, VotePage, VoteSummary,
+};
 
 #[macro_export]
 macro_rules! conn {
@@ -36,6 +47,27 @@ pub enum Error {
     #[error("failed to cast vote")]
     FailedToCastVote,
 
+    #[error("failed to retract vote")]
+    FailedToRetractVote,
+
+    #[error("failed to delete votes")]
+    FailedToDeleteVotes,
+
+    #[error("failed to update banned clients")]
+    FailedToUpdateBannedClients,
+
+    #[error("failed to save snap metadata")]
+    FailedToSaveSnap,
+
+    #[error("failed to create category")]
+    FailedToCreateCategory,
+
+    #[error("failed to update refresh tokens")]
+    FailedToUpdateRefreshTokens,
+
+    #[error("failed to revoke tokens")]
+    FailedToRevokeTokens,
+
     #[error(transparent)]
     Migration(#[from] sqlx::migrate::MigrateError),
 
@@ -75,6 +107,11 @@ pub async fn get_pool() -> Result<&'static PgPool> {
     Ok(pool)
 }
 
+/// Returns the connection pool if it has already been initialised.
+pub fn try_get_pool() -> Option<&'static PgPool> {
+    POOL.get()
+}
+
 pub async fn check_db_conn() -> Result<()> {
     conn!().ping().await.map_err(Into::into)
 }
@@ -82,7 +119,6 @@ pub async fn check_db_conn() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::conn;
     use sqlx::types::time::OffsetDateTime;
     use tracing_subscriber::EnvFilter;
 
@@ -154,17 +190,208 @@ mod tests {
         Ok(())
     }
 
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn daily_vote_counts_track_votes() -> Result<()> {
+        let conn = conn!();
+        let client_hash = "0000000000000000000000000000000000000000000000000000000000000003";
+        let snap_id = "00000000000000000000000000000004";
+        let snap_ids = [snap_id.to_string()];
+        User::create_or_seen(client_hash, conn).await?;
+
+        let vote = |snap_revision, vote_up| vote::Vote {
+            client_hash: client_hash.to_string(),
+            snap_id: snap_id.to_string(),
+            vote_up,
+            timestamp: OffsetDateTime::now_utc(),
+            snap_revision,
+        };
+        let counts = |summaries: Vec<VoteSummary>| {
+            summaries
+                .into_iter()
+                .map(|s| (s.total_votes, s.positive_votes))
+                .collect::<Vec<_>>()
+        };
+
+        vote(1, true).save_to_db(conn).await?;
+        vote(2, true).save_to_db(conn).await?;
+        vote(2, false).save_to_db(conn).await?;
+        let summaries =
+            VoteSummary::get_by_snap_ids(&snap_ids, Timeframe::Week, VoteDecay::None, conn).await?;
+        assert_eq!(counts(summaries), vec![(2, 1)]);
+
+        let now = OffsetDateTime::now_utc();
+        let range = DateRange::new(now - time::Duration::days(1), now).unwrap();
+        let summaries = VoteSummary::get_for_range(range, None, VoteDecay::None, conn).await?;
+        assert!(summaries.iter().any(|s| s.snap_id == snap_id));
+        let range = DateRange::new(
+            now - time::Duration::hours(2),
+            now - time::Duration::hours(1),
+        )
+        .unwrap();
+        let summaries = VoteSummary::get_for_range(range, None, VoteDecay::None, conn).await?;
+        assert!(!summaries.iter().any(|s| s.snap_id == snap_id));
+        let trending = VoteSummary::get_trending(None, VoteDecay::None, conn).await?;
+        assert!(trending.recent.iter().any(|s| s.snap_id == snap_id));
+        assert!(!trending.previous.iter().any(|s| s.snap_id == snap_id));
+
+        vote::Vote::delete(client_hash, snap_id, 1, conn).await?;
+        let summaries =
+            VoteSummary::get_by_snap_ids(&snap_ids, Timeframe::Year, VoteDecay::None, conn).await?;
+        assert_eq!(counts(summaries), vec![(1, 0)]);
+
+        vote::Vote::delete_all_for_snap(snap_id, conn).await?;
+        let summaries =
+            VoteSummary::get_by_snap_ids(&snap_ids, Timeframe::Unspecified, VoteDecay::None, conn)
+                .await?;
+        assert!(summaries.is_empty());
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn timeframes_exclude_votes_just_outside_them() -> Result<()> {
+        let conn = conn!();
+        let client_hash = "0000000000000000000000000000000000000000000000000000000000000007";
+        let snap_id = "00000000000000000000000000000006";
+        let snap_ids = [snap_id.to_string()];
+        User::create_or_seen(client_hash, conn).await?;
+
+        for (snap_revision, vote_up) in [(1, true), (2, false)] {
+            vote::Vote {
+                client_hash: client_hash.to_string(),
+                snap_id: snap_id.to_string(),
+                vote_up,
+                timestamp: OffsetDateTime::now_utc(),
+                snap_revision,
+            }
+            .save_to_db(conn)
+            .await?;
+        }
+        sqlx::query(
+            r#"
+            UPDATE votes SET created = NOW() - INTERVAL '1 week 1 minute'
+            WHERE snap_id = $1 AND snap_revision = 1;
+            "#,
+        )
+        .bind(snap_id)
+        .execute(&mut *conn)
+        .await?;
+
+        let counts = |summaries: Vec<VoteSummary>| {
+            summaries
+                .into_iter()
+                .map(|s| (s.total_votes, s.positive_votes))
+                .collect::<Vec<_>>()
+        };
+
+        let summaries =
+            VoteSummary::get_by_snap_ids(&snap_ids, Timeframe::Week, VoteDecay::None, conn).await?;
+        assert_eq!(counts(summaries), vec![(1, 0)], "week");
+        let summaries =
+            VoteSummary::get_by_snap_ids(&snap_ids, Timeframe::Month, VoteDecay::None, conn)
+                .await?;
+        assert_eq!(counts(summaries), vec![(2, 1)], "month");
+
+        let trending = VoteSummary::get_trending(None, VoteDecay::None, conn).await?;
+        let in_window = |summaries: Vec<VoteSummary>| {
+            counts(
+                summaries
+                    .into_iter()
+                    .filter(|s| s.snap_id == snap_id)
+                    .collect(),
+            )
+        };
+        assert_eq!(in_window(trending.recent), vec![(1, 0)], "recent");
+        assert_eq!(in_window(trending.previous), vec![(1, 1)], "previous");
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn refresh_tokens_can_only_be_redeemed_once() -> Result<()> {
+        let conn = conn!();
+        let client_hash = "0000000000000000000000000000000000000000000000000000000000000004";
+        let user = User::create_or_seen(client_hash, conn).await?;
+        let expires = OffsetDateTime::now_utc() + time::Duration::days(1);
+
+        create_refresh_token("valid", user.id, expires, conn).await?;
+        assert_eq!(
+            redeem_refresh_token("valid", conn).await?.as_deref(),
+            Some(client_hash)
+        );
+        assert_eq!(redeem_refresh_token("valid", conn).await?, None);
+
+        let expired = OffsetDateTime::now_utc() - time::Duration::minutes(1);
+        create_refresh_token("expired", user.id, expired, conn).await?;
+        assert_eq!(redeem_refresh_token("expired", conn).await?, None);
+
+        let (remaining,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM refresh_tokens WHERE token_hash IN ('valid', 'expired')",
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        assert_eq!(remaining, 0, "spent tokens should be pruned");
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn tokens_issued_before_a_revocation_are_revoked() -> Result<()> {
+        let conn = conn!();
+        let client_hash = "0000000000000000000000000000000000000000000000000000000000000005";
+        let revocation = TokenRevocation::now(time::Duration::days(1));
+        let before = revocation.revoked - time::Duration::minutes(1);
+        // Clients may authenticate again within the same second as the revocation
+        let after = revocation.revoked + time::Duration::milliseconds(1);
+
+        assert!(!User::is_token_revoked(client_hash, before, conn).await?);
+
+        User::revoke_tokens(client_hash, &revocation, conn).await?;
+        assert!(User::is_token_revoked(client_hash, before, conn).await?);
+        assert!(User::is_token_revoked(client_hash, revocation.revoked, conn).await?);
+        assert!(!User::is_token_revoked(client_hash, after, conn).await?);
+
+        let revocations = User::get_token_revocations(conn).await?;
+        assert_eq!(revocations.get(client_hash), Some(&revocation));
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn votes_from_unknown_users_are_not_saved() -> Result<()> {
+        let conn = conn!();
+        let vote = vote::Vote {
+            client_hash: "0000000000000000000000000000000000000000000000000000000000000006"
+                .to_string(),
+            snap_id: "00000000000000000000000000000005".to_string(),
+            vote_up: true,
+            timestamp: OffsetDateTime::now_utc(),
+            snap_revision: 1,
+        };
+
+        assert_eq!(vote.save_to_db(conn).await?, 0);
+
+        Ok(())
+    }
+
     #[cfg_attr(not(feature = "db_tests"), ignore)]
     #[tokio::test]
     async fn update_categories() -> Result<()> {
         let conn = conn!();
         let snap_id = "00000000000000000000000000000001";
-        let cats = vec![categories::Category::ArtAndDesign];
+        let art = Category::get_by_slug("art-and-design", conn)
+            .await?
+            .unwrap();
 
         assert!(!categories::snap_has_categories(snap_id, conn)
             .await
             .unwrap());
-        categories::set_categories_for_snap(snap_id, cats, conn)
+        categories::set_categories_for_snap(snap_id, vec![art], conn)
             .await
             .unwrap();
         assert!(categories::snap_has_categories(snap_id, conn)
@@ -173,4 +400,100 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn replace_categories() -> Result<()> {
+        let conn = conn!();
+        let snap_id = "00000000000000000000000000000002";
+        let slugs = ["games", "social", "utilities"].map(String::from);
+        let [games, social, utilities] =
+            <[Category; 3]>::try_from(Category::get_by_slugs(&slugs, conn).await?).unwrap();
+
+        categories::set_categories_for_snap(snap_id, vec![games.clone(), social.clone()], conn)
+            .await?;
+        let changed =
+            categories::set_categories_for_snap(snap_id, vec![social, games], conn).await?;
+        assert!(!changed, "the same categories in a different order");
+
+        let changed =
+            categories::set_categories_for_snap(snap_id, vec![utilities.clone()], conn).await?;
+        assert!(changed);
+        assert_eq!(
+            categories::categories_for_snap(snap_id, conn).await?,
+            vec![utilities]
+        );
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn quarantine_unknown_categories() -> Result<()> {
+        let conn = conn!();
+        let snap_id = "00000000000000000000000000000003";
+        let slugs = ["brand-new", "also-new"].map(String::from);
+
+        categories::quarantine_unknown_categories(snap_id, &slugs, conn).await?;
+        assert_eq!(
+            categories::unknown_categories_for_snap(snap_id, conn).await?,
+            vec!["also-new", "brand-new"]
+        );
+
+        categories::quarantine_unknown_categories(snap_id, &slugs[..1], conn).await?;
+        assert_eq!(
+            categories::unknown_categories_for_snap(snap_id, conn).await?,
+            vec!["brand-new"]
+        );
+
+        categories::quarantine_unknown_categories(snap_id, &[], conn).await?;
+        assert!(categories::unknown_categories_for_snap(snap_id, conn)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn refreshed_categories_are_not_stale() -> Result<()> {
+        let conn = conn!();
+        let snap_id = "00000000000000000000000000000004";
+        let max_age = time::Duration::hours(1);
+        Snap {
+            snap_id: snap_id.to_string(),
+            name: "stale-categories".to_string(),
+            publisher: None,
+            last_refreshed: OffsetDateTime::now_utc(),
+        }
+        .save_to_db(conn)
+        .await?;
+
+        categories::quarantine_unknown_categories(snap_id, &["brand-new".to_string()], conn)
+            .await?;
+        let stale = categories::snaps_with_stale_categories(max_age, conn).await?;
+        assert!(stale.iter().any(|id| id == snap_id), "{stale:?}");
+
+        categories::mark_categories_refreshed(snap_id, conn).await?;
+        let stale = categories::snaps_with_stale_categories(max_age, conn).await?;
+        assert!(!stale.iter().any(|id| id == snap_id), "{stale:?}");
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn create_category() -> Result<()> {
+        let conn = conn!();
+        let slug = format!("test-{}", OffsetDateTime::now_utc().unix_timestamp_nanos());
+
+        let category = Category::create(&slug, "Test Category", conn).await?;
+        assert!(category.is_some());
+        assert_eq!(Category::get_by_slug(&slug, conn).await?, category);
+
+        let duplicate = Category::create(&slug, "Duplicate", conn).await?;
+        assert!(duplicate.is_none());
+
+        Ok(())
+    }
 }
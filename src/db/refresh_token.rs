@@ -0,0 +1,66 @@
+//! Refresh tokens issued to users, which are identified by a hash of the token itself
+use crate::db::{ClientHash, Error, Result};
+use sqlx::{types::time::OffsetDateTime, PgConnection};
+use tracing::error;
+
+/// Stores the hash of a newly issued refresh token for the given user.
+pub async fn create_refresh_token(
+    token_hash: &str,
+    user_id: i32,
+    expires: OffsetDateTime,
+    conn: &mut PgConnection,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+    INSERT INTO refresh_tokens (token_hash, user_id_fk, expires)
+    VALUES ($1, $2, $3);
+    "#,
+    )
+    .bind(token_hash)
+    .bind(user_id)
+    .bind(expires)
+    .execute(conn)
+    .await
+    .map_err(|error| {
+        error!("{error:?}");
+        Error::FailedToUpdateRefreshTokens
+    })?;
+
+    Ok(())
+}
+
+/// Revokes a refresh token so that it can not be used again, returning the [`ClientHash`]
+/// it was issued to if the token was still valid.
+///
+/// Tokens that have previously been revoked or have expired can never be redeemed so they are
+/// pruned at the same time to stop the table growing without bound.
+pub async fn redeem_refresh_token(
+    token_hash: &str,
+    conn: &mut PgConnection,
+) -> Result<Option<ClientHash>> {
+    let client_hash: Option<(ClientHash,)> = sqlx::query_as(
+        r#"
+    WITH pruned AS (
+        DELETE FROM refresh_tokens
+        WHERE revoked IS NOT NULL OR expires <= NOW()
+    )
+    UPDATE refresh_tokens
+    SET revoked = NOW()
+    FROM users
+    WHERE refresh_tokens.token_hash = $1
+        AND refresh_tokens.revoked IS NULL
+        AND refresh_tokens.expires > NOW()
+        AND users.id = refresh_tokens.user_id_fk
+    RETURNING users.client_hash;
+    "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(conn)
+    .await
+    .map_err(|error| {
+        error!("{error:?}");
+        Error::FailedToUpdateRefreshTokens
+    })?;
+
+    Ok(client_hash.map(|(c,)| c))
+}
@@ -0,0 +1,113 @@
+use crate::db::{Error, Result};
+use sqlx::{prelude::FromRow, types::time::OffsetDateTime, PgConnection};
+use time::Duration;
+use tracing::error;
+
+/// Metadata about a snap from snapcraft.io, stored so that it only needs to be fetched again
+/// once it has become stale.
+#[derive(Debug, Clone, FromRow, PartialEq, Eq)]
+pub struct Snap {
+    /// The ID of the snap
+    pub snap_id: String,
+    /// The name of the snap
+    pub name: String,
+    /// The account ID of the snap's publisher, if known
+    pub publisher: Option<String>,
+    /// The time the metadata was last fetched from snapcraft.io
+    pub last_refreshed: OffsetDateTime,
+}
+
+impl Snap {
+    /// Whether or not the metadata is older than the given age and should be refreshed.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        OffsetDateTime::now_utc() - self.last_refreshed > max_age
+    }
+
+    /// Retrieves the stored metadata for any of the given snaps that we have seen before.
+    pub async fn get_by_snap_ids(
+        snap_ids: &[String],
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Self>> {
+        let snaps = sqlx::query_as(
+            r#"
+        SELECT snap_id, name, publisher, last_refreshed
+        FROM snaps
+        WHERE snap_id = ANY($1);
+        "#,
+        )
+        .bind(snap_ids)
+        .fetch_all(conn)
+        .await?;
+
+        Ok(snaps)
+    }
+
+    /// Saves the metadata for a [`Snap`], replacing anything we had stored for it previously.
+    pub async fn save_to_db(&self, conn: &mut PgConnection) -> Result<()> {
+        sqlx::query(
+            r#"
+        INSERT INTO snaps (snap_id, name, publisher, last_refreshed)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (snap_id)
+        DO UPDATE SET
+            name = EXCLUDED.name,
+            publisher = EXCLUDED.publisher,
+            last_refreshed = EXCLUDED.last_refreshed;
+        "#,
+        )
+        .bind(&self.snap_id)
+        .bind(&self.name)
+        .bind(&self.publisher)
+        .bind(self.last_refreshed)
+        .execute(conn)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToSaveSnap
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap_refreshed(ago: Duration) -> Snap {
+        Snap {
+            snap_id: "a".repeat(32),
+            name: "test-snap".to_string(),
+            publisher: None,
+            last_refreshed: OffsetDateTime::now_utc() - ago,
+        }
+    }
+
+    #[test]
+    fn recently_refreshed_snaps_are_not_stale() {
+        assert!(!snap_refreshed(Duration::minutes(5)).is_stale(Duration::hours(1)));
+    }
+
+    #[test]
+    fn old_snaps_are_stale() {
+        assert!(snap_refreshed(Duration::hours(2)).is_stale(Duration::hours(1)));
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn saved_snaps_can_be_read_back() -> Result<()> {
+        let conn = crate::conn!();
+        let mut snap = snap_refreshed(Duration::minutes(5));
+        snap.last_refreshed = snap.last_refreshed.replace_nanosecond(0).unwrap();
+
+        snap.save_to_db(conn).await?;
+        snap.name = "renamed-snap".to_string();
+        snap.publisher = Some("publisher".to_string());
+        snap.save_to_db(conn).await?;
+
+        let ids = [snap.snap_id.clone(), "b".repeat(32)];
+        assert_eq!(Snap::get_by_snap_ids(&ids, conn).await?, vec![snap]);
+
+        Ok(())
+    }
+}
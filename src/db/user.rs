@@ -1,5 +1,8 @@
 use crate::db::{ClientHash, Error, Result};
+use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, types::time::OffsetDateTime, PgConnection};
+use std::collections::HashMap;
+use time::Duration;
 use tracing::error;
 
 /// Information about a user who may be rating snaps.
@@ -15,6 +18,53 @@ pub struct User {
     pub last_seen: OffsetDateTime,
 }
 
+/// The revocation of every access token issued to a client up until a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRevocation {
+    /// Tokens issued up until this time are revoked
+    #[serde(with = "unix_micros")]
+    pub revoked: OffsetDateTime,
+    /// The time by which every token covered by the revocation has expired
+    #[serde(with = "unix_micros")]
+    pub expires: OffsetDateTime,
+}
+
+impl TokenRevocation {
+    /// A revocation of the tokens issued up until now, which are valid for at most `lifetime`.
+    ///
+    /// Postgres stores timestamps to the microsecond and token issue times are rounded when
+    /// encoded, so the time of the revocation is rounded up to the next microsecond and given a
+    /// microsecond of leeway: this errs on the side of revoking tokens issued at the same instant.
+    pub fn now(lifetime: Duration) -> Self {
+        let nanos = OffsetDateTime::now_utc().unix_timestamp_nanos();
+        let micros = (nanos + 999) / 1000 + 1;
+        let revoked = OffsetDateTime::from_unix_timestamp_nanos(micros * 1000)
+            .expect("the current time to be a valid timestamp");
+
+        Self {
+            revoked,
+            expires: revoked + lifetime,
+        }
+    }
+}
+
+/// (De)serializes timestamps as whole microseconds since the unix epoch, the precision that
+/// Postgres stores them with.
+mod unix_micros {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use sqlx::types::time::OffsetDateTime;
+
+    pub fn serialize<S: Serializer>(t: &OffsetDateTime, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_i64((t.unix_timestamp_nanos() / 1000) as i64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<OffsetDateTime, D::Error> {
+        let micros = i64::deserialize(d)?;
+
+        OffsetDateTime::from_unix_timestamp_nanos(micros as i128 * 1000).map_err(D::Error::custom)
+    }
+}
+
 impl User {
     /// Create a [`User`] entry, or note that the user has recently been seen
     pub async fn create_or_seen(client_hash: &str, conn: &mut PgConnection) -> Result<Self> {
@@ -55,4 +105,136 @@ impl User {
 
         Ok(())
     }
+
+    /// Bans the given [`ClientHash`], preventing it from authenticating or casting votes.
+    /// Banning a client that is already banned has no effect.
+    pub async fn ban(client_hash: &str, conn: &mut PgConnection) -> Result<()> {
+        sqlx::query(
+            r#"
+        INSERT INTO banned_clients (client_hash)
+        VALUES ($1)
+        ON CONFLICT (client_hash) DO NOTHING;
+        "#,
+        )
+        .bind(client_hash)
+        .execute(conn)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToUpdateBannedClients
+        })?;
+
+        Ok(())
+    }
+
+    /// Lifts a ban on the given [`ClientHash`], returning whether or not it was banned.
+    pub async fn unban(client_hash: &str, conn: &mut PgConnection) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+        DELETE FROM banned_clients
+        WHERE client_hash = $1;
+        "#,
+        )
+        .bind(client_hash)
+        .execute(conn)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToUpdateBannedClients
+        })?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revokes every access token issued to the given [`ClientHash`] up until the time of the
+    /// [`TokenRevocation`]. Any revocations which are no longer needed are removed at the same
+    /// time.
+    pub async fn revoke_tokens(
+        client_hash: &str,
+        revocation: &TokenRevocation,
+        conn: &mut PgConnection,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+        WITH expired AS (
+            DELETE FROM revoked_tokens
+            WHERE expires < NOW()
+        )
+        INSERT INTO revoked_tokens (client_hash, revoked, expires)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (client_hash)
+        DO UPDATE SET revoked = EXCLUDED.revoked, expires = EXCLUDED.expires;
+        "#,
+        )
+        .bind(client_hash)
+        .bind(revocation.revoked)
+        .bind(revocation.expires)
+        .execute(conn)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToRevokeTokens
+        })?;
+
+        Ok(())
+    }
+
+    /// Every revocation covering tokens which have not yet expired, keyed by [`ClientHash`].
+    pub async fn get_token_revocations(
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<ClientHash, TokenRevocation>> {
+        let rows: Vec<(ClientHash, OffsetDateTime, OffsetDateTime)> = sqlx::query_as(
+            r#"
+        SELECT client_hash, revoked, expires FROM revoked_tokens
+        WHERE expires >= NOW();
+        "#,
+        )
+        .fetch_all(conn)
+        .await?;
+
+        let revocations = rows
+            .into_iter()
+            .map(|(client_hash, revoked, expires)| {
+                (client_hash, TokenRevocation { revoked, expires })
+            })
+            .collect();
+
+        Ok(revocations)
+    }
+
+    /// Whether or not a token issued to the given [`ClientHash`] at `issued_at` has been revoked.
+    pub async fn is_token_revoked(
+        client_hash: &str,
+        issued_at: OffsetDateTime,
+        conn: &mut PgConnection,
+    ) -> Result<bool> {
+        let (revoked,): (bool,) = sqlx::query_as(
+            r#"
+        SELECT EXISTS (
+            SELECT 1 FROM revoked_tokens
+            WHERE client_hash = $1 AND revoked >= $2
+        );
+        "#,
+        )
+        .bind(client_hash)
+        .bind(issued_at)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(revoked)
+    }
+
+    /// Whether or not the given [`ClientHash`] has been banned.
+    pub async fn is_banned(client_hash: &str, conn: &mut PgConnection) -> Result<bool> {
+        let (banned,): (bool,) = sqlx::query_as(
+            r#"
+        SELECT EXISTS (SELECT 1 FROM banned_clients WHERE client_hash = $1);
+        "#,
+        )
+        .bind(client_hash)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(banned)
+    }
 }
@@ -1,6 +1,8 @@
-use crate::db::{categories::Category, ClientHash, Error, Result};
-use cached::proc_macro::cached;
-use sqlx::{types::time::OffsetDateTime, FromRow, PgConnection, QueryBuilder};
+use crate::db::{ClientHash, Error, Result};
+use sqlx::{
+    types::time::{Date, OffsetDateTime, Time, UtcOffset},
+    FromRow, PgConnection, Postgres, QueryBuilder,
+};
 use tracing::error;
 
 /// A Vote, as submitted by a user
@@ -20,6 +22,61 @@ pub struct Vote {
     pub timestamp: OffsetDateTime,
 }
 
+/// A [Vote] along with its row ID, used for paginating through votes.
+#[derive(Debug, FromRow)]
+struct VoteRow {
+    id: i32,
+    #[sqlx(flatten)]
+    vote: Vote,
+}
+
+/// A position within the votes cast by a client, used to resume listing them from where a
+/// previous page left off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoteCursor {
+    /// The creation time of the last vote returned
+    pub created: OffsetDateTime,
+    /// The ID of the last vote returned, used to break ties on `created`
+    pub id: i32,
+}
+
+impl VoteCursor {
+    /// Encodes the cursor as an opaque token that can be handed to clients.
+    pub fn encode(&self) -> String {
+        format!("{}.{}", self.created.unix_timestamp_nanos(), self.id)
+    }
+
+    /// Decodes a token previously produced by [VoteCursor::encode].
+    pub fn decode(token: &str) -> Option<Self> {
+        let (created, id) = token.split_once('.')?;
+        let created = OffsetDateTime::from_unix_timestamp_nanos(created.parse().ok()?).ok()?;
+        let id = id.parse().ok()?;
+
+        Some(Self { created, id })
+    }
+}
+
+/// The raw, unweighted vote counts for a single revision of a snap.
+#[derive(Debug, Clone, Copy, FromRow, PartialEq, Eq)]
+pub struct RevisionVoteCounts {
+    /// The revision of the snap
+    #[sqlx(try_from = "i32")]
+    pub snap_revision: u32,
+    /// The total number of votes cast for this revision
+    pub total_votes: i64,
+    /// The number of positive votes cast for this revision
+    pub positive_votes: i64,
+}
+
+/// A single page of the votes cast by a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VotePage {
+    /// The votes in this page, most recent first
+    pub votes: Vec<Vote>,
+    /// The position to resume from in order to fetch the next page, if there is one
+    pub next: Option<VoteCursor>,
+}
+
 impl Vote {
     /// Gets votes for a snap with the given ID from a given [`ClientHash`]
     ///
@@ -62,12 +119,85 @@ impl Vote {
         Ok(votes)
     }
 
-    /// Saves a [`Vote`] to the database, if possible.
+    /// Gets a page of up to `limit` votes across all snaps from a given [`ClientHash`], most
+    /// recent first, starting after the given [`VoteCursor`] if there is one.
+    ///
+    /// [`ClientHash`]: crate::db::ClientHash
+    pub async fn get_page_by_client_hash(
+        client_hash: &str,
+        after: Option<VoteCursor>,
+        limit: u32,
+        conn: &mut PgConnection,
+    ) -> Result<VotePage> {
+        let mut builder = QueryBuilder::new(
+            r#"
+                SELECT
+                    votes.id,
+                    votes.created,
+                    votes.snap_id,
+                    votes.snap_revision,
+                    votes.vote_up,
+                    users.client_hash
+                FROM
+                    users
+                INNER JOIN
+                    votes
+                ON
+                    users.id = votes.user_id_fk
+                WHERE
+                    users.client_hash = "#,
+        );
+        builder.push_bind(client_hash);
+
+        if let Some(VoteCursor { created, id }) = after {
+            builder
+                .push(" AND (votes.created, votes.id) < (")
+                .push_bind(created)
+                .push(", ")
+                .push_bind(id)
+                .push(")");
+        }
+
+        // Fetch an extra row so we know whether or not there is another page
+        builder
+            .push(" ORDER BY votes.created DESC, votes.id DESC LIMIT ")
+            .push_bind(limit as i64 + 1);
+
+        let mut rows: Vec<VoteRow> =
+            builder
+                .build_query_as()
+                .fetch_all(conn)
+                .await
+                .map_err(|error| {
+                    error!("{error:?}");
+                    Error::FailedToGetUserVote
+                })?;
+
+        let next = if rows.len() > limit as usize {
+            rows.truncate(limit as usize);
+            rows.last().map(|row| VoteCursor {
+                created: row.vote.timestamp,
+                id: row.id,
+            })
+        } else {
+            None
+        };
+
+        Ok(VotePage {
+            votes: rows.into_iter().map(|row| row.vote).collect(),
+            next,
+        })
+    }
+
+    /// Saves a [`Vote`] to the database, if possible, returning the number of votes saved. No vote
+    /// is saved if there is no user with the vote's [`ClientHash`].
+    ///
+    /// [`ClientHash`]: crate::db::ClientHash
     pub async fn save_to_db(self, conn: &mut PgConnection) -> Result<u64> {
         let result = sqlx::query(
             r#"
         INSERT INTO votes (user_id_fk, snap_id, snap_revision, vote_up)
-        VALUES ((SELECT id FROM users WHERE client_hash = $1), $2, $3, $4)
+        SELECT id, $2, $3, $4 FROM users WHERE client_hash = $1
         ON CONFLICT (user_id_fk, snap_id, snap_revision)
         DO UPDATE SET vote_up = EXCLUDED.vote_up;
         "#,
@@ -85,6 +215,111 @@ impl Vote {
 
         Ok(result.rows_affected())
     }
+
+    /// Deletes the vote cast from the given [`ClientHash`] for a specific revision of a snap,
+    /// returning the number of votes removed.
+    ///
+    /// [`ClientHash`]: crate::db::ClientHash
+    pub async fn delete(
+        client_hash: &str,
+        snap_id: &str,
+        snap_revision: u32,
+        conn: &mut PgConnection,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+        DELETE FROM votes
+        USING users
+        WHERE votes.user_id_fk = users.id
+        AND users.client_hash = $1
+        AND votes.snap_id = $2
+        AND votes.snap_revision = $3;
+        "#,
+        )
+        .bind(client_hash)
+        .bind(snap_id)
+        .bind(snap_revision as i32)
+        .execute(conn)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToRetractVote
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes every vote cast for the given snap, returning the number of votes removed.
+    pub async fn delete_all_for_snap(snap_id: &str, conn: &mut PgConnection) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+        DELETE FROM votes
+        WHERE snap_id = $1;
+        "#,
+        )
+        .bind(snap_id)
+        .execute(conn)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToDeleteVotes
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes every vote cast from the given [`ClientHash`], returning the IDs of the snaps
+    /// that the removed votes were for, with one entry per vote removed.
+    ///
+    /// [`ClientHash`]: crate::db::ClientHash
+    pub async fn delete_all_by_client_hash(
+        client_hash: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<String>> {
+        let snap_ids: Vec<(String,)> = sqlx::query_as(
+            r#"
+        DELETE FROM votes
+        USING users
+        WHERE votes.user_id_fk = users.id
+        AND users.client_hash = $1
+        RETURNING votes.snap_id;
+        "#,
+        )
+        .bind(client_hash)
+        .fetch_all(conn)
+        .await
+        .map_err(|error| {
+            error!("{error:?}");
+            Error::FailedToDeleteVotes
+        })?;
+
+        Ok(snap_ids.into_iter().map(|(snap_id,)| snap_id).collect())
+    }
+
+    /// Retrieves the raw vote counts for each revision of a snap that has been voted on, most
+    /// recent revision first.
+    pub async fn counts_by_revision(
+        snap_id: &str,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<RevisionVoteCounts>> {
+        let counts = sqlx::query_as(
+            r#"
+        SELECT
+            snap_revision,
+            COUNT(*) AS total_votes,
+            COUNT(*) FILTER (WHERE vote_up) AS positive_votes
+        FROM votes
+        WHERE snap_id = $1
+        GROUP BY snap_revision
+        ORDER BY snap_revision DESC;
+        "#,
+        )
+        .bind(snap_id)
+        .fetch_all(conn)
+        .await?;
+
+        Ok(counts)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::FromRepr)]
@@ -93,6 +328,242 @@ pub enum Timeframe {
     Unspecified,
     Week,
     Month,
+    /// Votes from the most recent [TRENDING_WINDOW], compared against the window before it
+    Trending,
+    Year,
+}
+
+/// The length of each of the windows that are compared when calculating a trending chart, as a
+/// Postgres interval.
+pub const TRENDING_WINDOW: &str = "1 week";
+
+impl Timeframe {
+    /// How far back votes are included for this timeframe, as a Postgres interval.
+    fn interval(&self) -> Option<&'static str> {
+        match self {
+            Self::Week => Some("1 week"),
+            Self::Month => Some("1 month"),
+            Self::Year => Some("1 year"),
+            Self::Trending => Some(TRENDING_WINDOW),
+            Self::Unspecified => None,
+        }
+    }
+
+    /// The [Period] covered by this timeframe, ending now, or None if it covers every vote.
+    fn period(&self) -> Option<Period> {
+        self.interval().map(|interval| Period::Ago {
+            interval,
+            from: 1,
+            until: 0,
+        })
+    }
+}
+
+/// An explicit period of time to restrict votes to, from `start` inclusive until `end`
+/// exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DateRange {
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+}
+
+impl DateRange {
+    /// Creates a new range, returning an error if `start` is not before `end`.
+    pub fn new(start: OffsetDateTime, end: OffsetDateTime) -> std::result::Result<Self, String> {
+        if start >= end {
+            return Err(format!("range start {start} must be before its end {end}"));
+        }
+
+        Ok(Self { start, end })
+    }
+
+    /// The start of the range, inclusive
+    pub fn start(&self) -> OffsetDateTime {
+        self.start
+    }
+
+    /// The end of the range, exclusive
+    pub fn end(&self) -> OffsetDateTime {
+        self.end
+    }
+
+    /// Appends the condition restricting votes to those in this range to a `WHERE` clause.
+    fn push_filter(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        builder
+            .push(" AND votes.created >= ")
+            .push_bind(self.start)
+            .push(" AND votes.created < ")
+            .push_bind(self.end);
+    }
+
+    /// The UTC days that lie entirely within this range, from the first day inclusive until the
+    /// last day exclusive. This is empty if the range does not cover a whole day.
+    fn whole_days(&self) -> (Date, Date) {
+        let start = self.start.to_offset(UtcOffset::UTC);
+        let first = if start.time() == Time::MIDNIGHT {
+            start.date()
+        } else {
+            start.date().next_day().unwrap_or(Date::MAX)
+        };
+
+        (first, self.end.to_offset(UtcOffset::UTC).date())
+    }
+}
+
+/// A period of time to summarise votes over, from its start inclusive until its end exclusive.
+#[derive(Debug, Clone, Copy)]
+enum Period {
+    /// An explicit [DateRange]
+    Range(DateRange),
+    /// From `from` until `until` multiples of a Postgres interval before now
+    Ago {
+        interval: &'static str,
+        from: i32,
+        until: i32,
+    },
+}
+
+impl Period {
+    /// Appends the start of this period as a timestamp.
+    fn push_start(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match *self {
+            Self::Range(range) => {
+                builder.push_bind(range.start);
+            }
+            Self::Ago { interval, from, .. } => push_ago(builder, interval, from),
+        }
+    }
+
+    /// Appends the end of this period as a timestamp.
+    fn push_end(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match *self {
+            Self::Range(range) => {
+                builder.push_bind(range.end);
+            }
+            Self::Ago {
+                interval, until, ..
+            } => push_ago(builder, interval, until),
+        }
+    }
+
+    /// Appends the first UTC day that lies entirely within this period.
+    fn push_first_whole_day(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match *self {
+            Self::Range(range) => {
+                builder.push_bind(range.whole_days().0);
+            }
+            Self::Ago { .. } => {
+                builder.push("((");
+                self.push_start(builder);
+                builder.push(") AT TIME ZONE 'UTC')::DATE + 1");
+            }
+        }
+    }
+
+    /// Appends the UTC day after the last day that lies entirely within this period.
+    fn push_last_whole_day(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match *self {
+            Self::Range(range) => {
+                builder.push_bind(range.whole_days().1);
+            }
+            Self::Ago { .. } => {
+                builder.push("((");
+                self.push_end(builder);
+                builder.push(") AT TIME ZONE 'UTC')::DATE");
+            }
+        }
+    }
+
+    /// Appends a `range_votes` derived table holding the votes cast within this period to a
+    /// `FROM` clause. Whole UTC days are read from the daily vote counts while the partial days at
+    /// either end of the period are read from the votes table, so the period is applied exactly.
+    fn push_source(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        builder.push(
+            r#" (
+                SELECT
+                    snap_id,
+                    total_votes,
+                    positive_votes,
+                    (day + TIME '12:00') AT TIME ZONE 'UTC' AS created
+                FROM daily_vote_counts
+                WHERE day >= "#,
+        );
+        self.push_first_whole_day(builder);
+        builder.push(" AND day < ");
+        self.push_last_whole_day(builder);
+        builder.push(
+            r#"
+                UNION ALL
+                SELECT snap_id, 1, vote_up::INT, created
+                FROM votes
+                WHERE created >= "#,
+        );
+        self.push_start(builder);
+        builder.push(" AND created < ");
+        self.push_end(builder);
+        builder.push(" AND NOT ((created AT TIME ZONE 'UTC')::DATE >= ");
+        self.push_first_whole_day(builder);
+        builder.push(" AND (created AT TIME ZONE 'UTC')::DATE < ");
+        self.push_last_whole_day(builder);
+        builder.push(")) AS range_votes");
+    }
+}
+
+/// Appends the time `n` multiples of the given Postgres interval before now.
+fn push_ago(builder: &mut QueryBuilder<'_, Postgres>, interval: &'static str, n: i32) {
+    builder
+        .push("(NOW() - ")
+        .push_bind(n)
+        .push(" * ")
+        .push_bind(interval)
+        .push("::INTERVAL)");
+}
+
+/// The vote summaries for the two windows compared when calculating a trending chart.
+#[derive(Debug, Clone)]
+pub struct TrendingSummaries {
+    /// Summaries of the votes cast within the most recent [TRENDING_WINDOW]
+    pub recent: Vec<VoteSummary>,
+    /// Summaries of the votes cast within the [TRENDING_WINDOW] before that
+    pub previous: Vec<VoteSummary>,
+}
+
+/// Which revisions of a snap should be included when summarising its votes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RevisionFilter {
+    /// Votes for every revision of the snap
+    #[default]
+    All,
+    /// Only votes for this exact revision
+    Exact(u32),
+    /// Only votes for the N most recent revisions that have been voted on
+    Latest(u32),
+}
+
+/// How much weight older votes carry when calculating the weighted totals of a [VoteSummary].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum VoteDecay {
+    /// Every vote counts equally regardless of age
+    #[default]
+    None,
+    /// The weight of a vote halves every `half_life_days` days after it was cast
+    Exponential { half_life_days: f64 },
+}
+
+impl VoteDecay {
+    /// Checks that the decay parameters are within their valid ranges.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match *self {
+            Self::Exponential { half_life_days }
+                if !half_life_days.is_finite() || half_life_days <= 0.0 =>
+            {
+                Err(format!(
+                    "vote decay half life must be positive and finite: {half_life_days}"
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// A summary of votes for a given snap, this is then aggregated before transfer.
@@ -104,123 +575,339 @@ pub struct VoteSummary {
     pub total_votes: i64,
     /// The number of the votes which are positive.
     pub positive_votes: i64,
+    /// The total votes weighted by their age according to the [VoteDecay] of the query.
+    pub weighted_total_votes: f64,
+    /// The positive votes weighted by their age according to the [VoteDecay] of the query.
+    pub weighted_positive_votes: f64,
 }
 
 impl VoteSummary {
-    pub async fn get_by_snap_id(snap_id: &str, conn: &mut PgConnection) -> Result<VoteSummary> {
-        get_by_snap_id_cached(snap_id, conn).await
+    /// An empty summary for a snap that has no votes.
+    pub fn empty(snap_id: &str) -> Self {
+        Self {
+            snap_id: snap_id.to_string(),
+            total_votes: 0,
+            positive_votes: 0,
+            weighted_total_votes: 0.0,
+            weighted_positive_votes: 0.0,
+        }
+    }
+
+    /// Retrieves the vote summary for a single snap, restricted to the revisions selected by the
+    /// given [RevisionFilter] and optionally to votes cast within a [DateRange].
+    pub async fn get_by_snap_id(
+        snap_id: &str,
+        revisions: RevisionFilter,
+        range: Option<DateRange>,
+        decay: VoteDecay,
+        conn: &mut PgConnection,
+    ) -> Result<VoteSummary> {
+        let mut builder = summary_query(decay);
+        builder.push(" WHERE votes.snap_id = ").push_bind(snap_id);
+
+        if let Some(range) = range {
+            range.push_filter(&mut builder);
+        }
+
+        match revisions {
+            RevisionFilter::All => (),
+            RevisionFilter::Exact(revision) => {
+                builder
+                    .push(" AND votes.snap_revision = ")
+                    .push_bind(revision as i32);
+            }
+            RevisionFilter::Latest(n) => {
+                builder
+                    .push(
+                        r#"
+                AND votes.snap_revision IN (
+                    SELECT DISTINCT v.snap_revision FROM votes v
+                    WHERE v.snap_id = "#,
+                    )
+                    .push_bind(snap_id)
+                    .push(" ORDER BY v.snap_revision DESC LIMIT ")
+                    .push_bind(n as i64)
+                    .push(")");
+            }
+        }
+
+        builder.push(" GROUP BY votes.snap_id");
+
+        let result: Option<VoteSummary> = builder.build_query_as().fetch_optional(conn).await?;
+        let summary = result.unwrap_or_else(|| VoteSummary::empty(snap_id));
+
+        Ok(summary)
     }
 
     pub async fn get_by_snap_ids(
         snap_ids: &[String],
         timeframe: Timeframe,
+        decay: VoteDecay,
         conn: &mut PgConnection,
     ) -> Result<Vec<VoteSummary>> {
         if snap_ids.is_empty() {
             return Ok(vec![]);
         }
 
-        let mut builder = QueryBuilder::new(
-            r#"
-            SELECT
-                votes.snap_id,
-                COUNT(*) AS total_votes,
-                COUNT(*) FILTER (WHERE votes.vote_up) AS positive_votes
-            FROM
-                votes
-            WHERE
-                votes.snap_id = ANY($1)
-        "#,
-        );
-
-        builder.push(match timeframe {
-            Timeframe::Week => " AND votes.created >= NOW() - INTERVAL '1 week'",
-            Timeframe::Month => " AND votes.created >= NOW() - INTERVAL '1 month'",
-            Timeframe::Unspecified => "",
-        });
-
-        builder.push(" GROUP BY votes.snap_id");
+        let mut builder = period_summary_query(timeframe.period(), decay);
+        builder
+            .push(" AND range_votes.snap_id = ANY(")
+            .push_bind(snap_ids)
+            .push(")");
+        builder.push(PERIOD_SUMMARY_GROUP_BY);
 
-        let summaries = builder
-            .build_query_as()
-            .bind(snap_ids)
-            .fetch_all(conn)
-            .await?;
+        let summaries = builder.build_query_as().fetch_all(conn).await?;
 
         Ok(summaries)
     }
 
-    /// Retrieves the vote summary over a given [Timeframe], optionally for a specific [Category]
+    /// Retrieves the vote summary over a given [Timeframe], optionally for the category with the
+    /// given ID
     pub async fn get_for_timeframe(
         timeframe: Timeframe,
-        category: Option<Category>,
+        category_id: Option<i32>,
+        decay: VoteDecay,
         conn: &mut PgConnection,
     ) -> Result<Vec<VoteSummary>> {
-        let mut builder = QueryBuilder::new(
-            r"
-            SELECT
-                votes.snap_id,
-                COUNT(*) AS total_votes,
-                COUNT(*) FILTER (WHERE votes.vote_up) AS positive_votes
-            FROM
-                votes",
-        );
+        get_in_category(timeframe.period(), category_id, decay, conn).await
+    }
 
-        builder.push(match timeframe {
-            Timeframe::Week => " WHERE votes.created >= NOW() - INTERVAL '1 week'",
-            Timeframe::Month => " WHERE votes.created >= NOW() - INTERVAL '1 month'",
-            Timeframe::Unspecified => "",
-        });
+    /// Retrieves the vote summary for votes cast within a [DateRange], optionally for the
+    /// category with the given ID
+    pub async fn get_for_range(
+        range: DateRange,
+        category_id: Option<i32>,
+        decay: VoteDecay,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<VoteSummary>> {
+        get_in_category(Some(Period::Range(range)), category_id, decay, conn).await
+    }
 
-        if let Some(category) = category {
-            builder
-                .push(
-                    r" 
-                    WHERE votes.snap_id IN (
-                    SELECT snap_categories.snap_id FROM snap_categories 
-                    WHERE snap_categories.category = ",
-                )
-                .push_bind(category)
-                .push(")");
-        }
+    /// Retrieves the vote summaries for the current and previous [TRENDING_WINDOW]s, optionally
+    /// for the category with the given ID.
+    pub async fn get_trending(
+        category_id: Option<i32>,
+        decay: VoteDecay,
+        conn: &mut PgConnection,
+    ) -> Result<TrendingSummaries> {
+        let recent =
+            get_in_category(Timeframe::Trending.period(), category_id, decay, conn).await?;
+        let previous = Period::Ago {
+            interval: TRENDING_WINDOW,
+            from: 2,
+            until: 1,
+        };
+        let previous = get_in_category(Some(previous), category_id, decay, conn).await?;
 
-        builder.push(" GROUP BY votes.snap_id");
-        let summaries = builder.build_query_as().fetch_all(conn).await?;
+        Ok(TrendingSummaries { recent, previous })
+    }
+}
 
-        Ok(summaries)
+/// Retrieves the vote summaries for the votes cast within the given [Period], or for every vote
+/// if there is none, optionally for the category with the given ID.
+async fn get_in_category(
+    period: Option<Period>,
+    category_id: Option<i32>,
+    decay: VoteDecay,
+    conn: &mut PgConnection,
+) -> Result<Vec<VoteSummary>> {
+    let mut builder = period_summary_query(period, decay);
+    push_category_filter(&mut builder, "range_votes.snap_id", category_id);
+
+    builder.push(PERIOD_SUMMARY_GROUP_BY);
+    let summaries = builder.build_query_as().fetch_all(conn).await?;
+
+    Ok(summaries)
+}
+
+/// Appends the condition restricting `snap_id` to the snaps in the given category, if any, to a
+/// `WHERE` clause.
+fn push_category_filter(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    snap_id: &str,
+    category_id: Option<i32>,
+) {
+    if let Some(category_id) = category_id {
+        builder
+            .push(format!(
+                r"
+                    AND {snap_id} IN (
+                    SELECT snap_categories.snap_id FROM snap_categories
+                    WHERE snap_categories.category = "
+            ))
+            .push_bind(category_id)
+            .push(")");
     }
 }
 
-#[cfg_attr(not(feature = "skip_cache"), cached(
-    time = 86400, // 24 hours
-    sync_writes = true,
-    key = "String",
-    convert = r##"{String::from(snap_id)}"##,
-    result = true,
-))]
-async fn get_by_snap_id_cached(snap_id: &str, conn: &mut PgConnection) -> Result<VoteSummary> {
-    let result: Option<VoteSummary> = sqlx::query_as(
+/// The start of a query selecting [VoteSummary] rows from the votes table, weighting votes
+/// according to the given [VoteDecay]. Callers are expected to add any filtering along with a
+/// `GROUP BY votes.snap_id` clause.
+fn summary_query<'a>(decay: VoteDecay) -> QueryBuilder<'a, Postgres> {
+    let mut builder = QueryBuilder::new(
         r#"
             SELECT
                 votes.snap_id,
                 COUNT(*) AS total_votes,
-                COUNT(*) FILTER (WHERE votes.vote_up) AS positive_votes
-            FROM
-                votes
-            WHERE
-                votes.snap_id = $1
-            GROUP BY votes.snap_id
-        "#,
-    )
-    .bind(snap_id)
-    .fetch_optional(conn)
-    .await?;
-
-    let summary = result.unwrap_or_else(|| VoteSummary {
-        snap_id: snap_id.to_string(),
-        total_votes: 0,
-        positive_votes: 0,
-    });
-
-    Ok(summary)
+                COUNT(*) FILTER (WHERE votes.vote_up) AS positive_votes,"#,
+    );
+    push_weighted_totals(
+        &mut builder,
+        decay,
+        "1",
+        "votes.vote_up::INT",
+        "votes.created",
+    );
+    builder.push(" FROM votes");
+
+    builder
+}
+
+/// The grouping that must follow the filtering of a [period_summary_query], skipping snaps whose
+/// votes have all since been removed.
+const PERIOD_SUMMARY_GROUP_BY: &str =
+    " GROUP BY range_votes.snap_id HAVING SUM(range_votes.total_votes) > 0";
+
+/// The start of a query selecting [VoteSummary] rows for the votes cast within the given
+/// [Period], or for every vote if there is none, which reads whole days from the daily vote
+/// counts to avoid scanning the full votes table. When decaying votes, those read from the daily
+/// counts are all treated as having been cast at midday. Callers are expected to add any
+/// filtering to the `WHERE` clause along with [PERIOD_SUMMARY_GROUP_BY].
+fn period_summary_query<'a>(
+    period: Option<Period>,
+    decay: VoteDecay,
+) -> QueryBuilder<'a, Postgres> {
+    let mut builder = QueryBuilder::new(
+        r#"
+            SELECT
+                range_votes.snap_id,
+                SUM(range_votes.total_votes)::INT8 AS total_votes,
+                SUM(range_votes.positive_votes)::INT8 AS positive_votes,"#,
+    );
+    push_weighted_totals(
+        &mut builder,
+        decay,
+        "range_votes.total_votes",
+        "range_votes.positive_votes",
+        "range_votes.created",
+    );
+    builder.push(" FROM");
+
+    match period {
+        Some(period) => period.push_source(&mut builder),
+        None => {
+            builder.push(
+                r#" (
+                SELECT
+                    snap_id,
+                    total_votes,
+                    positive_votes,
+                    (day + TIME '12:00') AT TIME ZONE 'UTC' AS created
+                FROM daily_vote_counts
+            ) AS range_votes"#,
+            );
+        }
+    }
+    builder.push(" WHERE TRUE");
+
+    builder
+}
+
+/// Appends the `weighted_total_votes` and `weighted_positive_votes` columns of a [VoteSummary]
+/// to a query, weighting votes according to the given [VoteDecay]. `total` and `positive` are
+/// the expressions for the number of votes in each row and `created` is the expression for when
+/// they were cast.
+fn push_weighted_totals(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    decay: VoteDecay,
+    total: &str,
+    positive: &str,
+    created: &str,
+) {
+    match decay {
+        VoteDecay::None => {
+            builder.push(format!(
+                r#"
+                SUM({total})::FLOAT8 AS weighted_total_votes,
+                SUM({positive})::FLOAT8 AS weighted_positive_votes"#
+            ));
+        }
+        VoteDecay::Exponential { half_life_days } => {
+            let half_life_secs = half_life_days * 86400.0;
+            let weight = format!(
+                "POWER(0.5::FLOAT8, GREATEST(EXTRACT(EPOCH FROM NOW() - {created}), 0)::FLOAT8 / "
+            );
+            builder
+                .push(format!("\n                SUM({total} * {weight}"))
+                .push_bind(half_life_secs)
+                .push(format!(
+                    ")) AS weighted_total_votes,\n                SUM({positive} * {weight}"
+                ))
+                .push_bind(half_life_secs)
+                .push(")) AS weighted_positive_votes");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_test_case::test_case;
+
+    #[test]
+    fn non_finite_half_lives_are_rejected() {
+        for half_life_days in [f64::NAN, f64::INFINITY, 0.0] {
+            let decay = VoteDecay::Exponential { half_life_days };
+            assert!(decay.validate().is_err(), "{half_life_days}");
+        }
+        assert!(VoteDecay::Exponential {
+            half_life_days: 30.0
+        }
+        .validate()
+        .is_ok());
+    }
+
+    #[test]
+    fn vote_cursor_round_trips() {
+        let cursor = VoteCursor {
+            created: OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_000).unwrap(),
+            id: 42,
+        };
+
+        assert_eq!(VoteCursor::decode(&cursor.encode()), Some(cursor));
+    }
+
+    #[test]
+    fn invalid_vote_cursors_are_rejected() {
+        for token in ["", "123", "abc.1", "1.abc", "1.2.3"] {
+            assert_eq!(VoteCursor::decode(token), None, "{token:?}");
+        }
+    }
+
+    #[test]
+    fn date_ranges_must_end_after_they_start() {
+        let start = OffsetDateTime::from_unix_timestamp(1_735_689_600).unwrap();
+        let end = OffsetDateTime::from_unix_timestamp(1_767_225_600).unwrap();
+
+        assert!(DateRange::new(start, end).is_ok());
+        assert!(DateRange::new(end, start).is_err());
+        assert!(DateRange::new(start, start).is_err());
+    }
+
+    #[test_case((1, 0), (3, 0), (1, 3); "aligned to days")]
+    #[test_case((1, 6), (3, 18), (2, 3); "partial days at both ends")]
+    #[test_case((1, 6), (1, 18), (2, 1); "within a single day")]
+    #[test]
+    fn whole_days_exclude_partial_days(start: (u8, u8), end: (u8, u8), (first, last): (u8, u8)) {
+        let day = |d| Date::from_calendar_date(2025, time::Month::January, d).unwrap();
+        let at = |(d, h)| {
+            day(d)
+                .with_time(Time::from_hms(h, 0, 0).unwrap())
+                .assume_utc()
+        };
+
+        let range = DateRange::new(at(start), at(end)).unwrap();
+
+        assert_eq!(range.whole_days(), (day(first), day(last)));
+    }
 }
@@ -0,0 +1,242 @@
+use crate::{
+    cache::{invalidate, Invalidation},
+    conn,
+    db::{self, Category, User, Vote},
+    grpc::{invalidate_cached_ratings, user::EXPECTED_CLIENT_HASH_LENGTH},
+    proto::admin::{
+        admin_server::{self, AdminServer},
+        BanClientRequest, CreateCategoryRequest, CreateCategoryResponse, DeleteClientVotesRequest,
+        DeleteSnapVotesRequest, DeleteVotesResponse, GetSnapVoteCountsRequest,
+        GetSnapVoteCountsResponse, RevisionVoteCounts, UnbanClientRequest,
+    },
+    Context,
+};
+use sqlx::{Connection, PgConnection};
+use std::{collections::HashSet, sync::Arc};
+use tonic::{Request, Response, Status};
+use tracing::{error, info};
+
+/// The service for moderating votes, only accessible using the admin credentials.
+#[derive(Clone)]
+pub struct AdminService {
+    ctx: Arc<Context>,
+}
+
+impl AdminService {
+    pub fn new_server(ctx: Arc<Context>) -> AdminServer<AdminService> {
+        AdminServer::new(Self { ctx })
+    }
+}
+
+#[tonic::async_trait]
+impl admin_server::Admin for AdminService {
+    async fn get_snap_vote_counts(
+        &self,
+        request: Request<GetSnapVoteCountsRequest>,
+    ) -> Result<Response<GetSnapVoteCountsResponse>, Status> {
+        let GetSnapVoteCountsRequest { snap_id } = request.into_inner();
+        if snap_id.is_empty() {
+            return Err(Status::invalid_argument("snap id"));
+        }
+
+        let counts = Vote::counts_by_revision(&snap_id, conn!())
+            .await
+            .map_err(|e| {
+                error!("Error in counts_by_revision: {:?}", e);
+                Status::unknown("Internal server error")
+            })?;
+
+        let revisions: Vec<RevisionVoteCounts> = counts
+            .into_iter()
+            .map(|c| RevisionVoteCounts {
+                snap_revision: c.snap_revision as i32,
+                total_votes: c.total_votes as u64,
+                positive_votes: c.positive_votes as u64,
+            })
+            .collect();
+
+        Ok(Response::new(GetSnapVoteCountsResponse {
+            snap_id,
+            total_votes: revisions.iter().map(|r| r.total_votes).sum(),
+            positive_votes: revisions.iter().map(|r| r.positive_votes).sum(),
+            revisions,
+        }))
+    }
+
+    async fn delete_snap_votes(
+        &self,
+        request: Request<DeleteSnapVotesRequest>,
+    ) -> Result<Response<DeleteVotesResponse>, Status> {
+        let DeleteSnapVotesRequest { snap_id } = request.into_inner();
+        if snap_id.is_empty() {
+            return Err(Status::invalid_argument("snap id"));
+        }
+
+        let conn = conn!();
+
+        match Vote::delete_all_for_snap(&snap_id, conn).await {
+            Ok(deleted_votes) => {
+                info!(%snap_id, deleted_votes, "admin deleted votes for snap");
+                invalidate_cached_ratings(&self.ctx, &snap_id, conn).await;
+
+                Ok(Response::new(DeleteVotesResponse { deleted_votes }))
+            }
+
+            Err(e) => {
+                error!("Error in delete_all_for_snap: {:?}", e);
+                Err(Status::unknown("Internal server error"))
+            }
+        }
+    }
+
+    async fn delete_client_votes(
+        &self,
+        request: Request<DeleteClientVotesRequest>,
+    ) -> Result<Response<DeleteVotesResponse>, Status> {
+        let DeleteClientVotesRequest { client_hash } = request.into_inner();
+        if client_hash.len() != EXPECTED_CLIENT_HASH_LENGTH {
+            return Err(invalid_client_hash());
+        }
+
+        let conn = conn!();
+        let snap_ids = Vote::delete_all_by_client_hash(&client_hash, conn)
+            .await
+            .map_err(|e| {
+                error!("Error in delete_all_by_client_hash: {:?}", e);
+                Status::unknown("Internal server error")
+            })?;
+        let deleted_votes = invalidate_client_votes(&self.ctx, &client_hash, snap_ids, conn).await;
+
+        Ok(Response::new(DeleteVotesResponse { deleted_votes }))
+    }
+
+    async fn ban_client(&self, request: Request<BanClientRequest>) -> Result<Response<()>, Status> {
+        let BanClientRequest {
+            client_hash,
+            delete_votes,
+        } = request.into_inner();
+        if client_hash.len() != EXPECTED_CLIENT_HASH_LENGTH {
+            return Err(invalid_client_hash());
+        }
+
+        let conn = conn!();
+
+        // The ban and the removal of the client's votes either both happen or neither does
+        let res = async {
+            let mut tx = conn.begin().await?;
+            User::ban(&client_hash, &mut tx).await?;
+            let snap_ids = if delete_votes {
+                Vote::delete_all_by_client_hash(&client_hash, &mut tx).await?
+            } else {
+                Vec::new()
+            };
+            tx.commit().await?;
+
+            Ok::<_, db::Error>(snap_ids)
+        };
+
+        let snap_ids = res.await.map_err(|e| {
+            error!("Error in ban: {:?}", e);
+            Status::unknown("Internal server error")
+        })?;
+        info!(%client_hash, "admin banned client");
+
+        // Caches are only invalidated once the votes are gone so that other replicas do not
+        // repopulate them from the votes we are removing
+        if delete_votes {
+            invalidate_client_votes(&self.ctx, &client_hash, snap_ids, conn).await;
+        }
+
+        Ok(Response::new(()))
+    }
+
+    async fn unban_client(
+        &self,
+        request: Request<UnbanClientRequest>,
+    ) -> Result<Response<()>, Status> {
+        let UnbanClientRequest { client_hash } = request.into_inner();
+        if client_hash.len() != EXPECTED_CLIENT_HASH_LENGTH {
+            return Err(invalid_client_hash());
+        }
+
+        match User::unban(&client_hash, conn!()).await {
+            Ok(false) => Err(Status::not_found("client is not banned")),
+
+            Ok(true) => {
+                info!(%client_hash, "admin unbanned client");
+                Ok(Response::new(()))
+            }
+
+            Err(e) => {
+                error!("Error in unban: {:?}", e);
+                Err(Status::unknown("Internal server error"))
+            }
+        }
+    }
+
+    async fn flush_caches(&self, _request: Request<()>) -> Result<Response<()>, Status> {
+        invalidate(&self.ctx, &[Invalidation::All], conn!()).await;
+        info!("admin flushed caches");
+
+        Ok(Response::new(()))
+    }
+
+    async fn create_category(
+        &self,
+        request: Request<CreateCategoryRequest>,
+    ) -> Result<Response<CreateCategoryResponse>, Status> {
+        let CreateCategoryRequest { slug, name } = request.into_inner();
+        if slug.is_empty() {
+            return Err(Status::invalid_argument("slug"));
+        }
+        if name.is_empty() {
+            return Err(Status::invalid_argument("name"));
+        }
+
+        match Category::create(&slug, &name, conn!()).await {
+            Ok(Some(category)) => {
+                info!(%slug, id = category.id, "admin created category");
+                Ok(Response::new(CreateCategoryResponse { id: category.id }))
+            }
+
+            Ok(None) => Err(Status::already_exists("category already exists")),
+
+            Err(e) => {
+                error!("Error in create category: {:?}", e);
+                Err(Status::unknown("Internal server error"))
+            }
+        }
+    }
+}
+
+fn invalid_client_hash() -> Status {
+    Status::invalid_argument(format!(
+        "Client hash must be of length {EXPECTED_CLIENT_HASH_LENGTH}"
+    ))
+}
+
+/// Invalidates the cached ratings for the snaps whose votes from a client have been deleted,
+/// returning the number of votes that were deleted.
+async fn invalidate_client_votes(
+    ctx: &Context,
+    client_hash: &str,
+    snap_ids: Vec<String>,
+    conn: &mut PgConnection,
+) -> u64 {
+    let deleted_votes = snap_ids.len() as u64;
+    info!(%client_hash, deleted_votes, "admin deleted votes for client");
+
+    if deleted_votes > 0 {
+        let mut invalidations: Vec<Invalidation> = snap_ids
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|snap_id| Invalidation::Snap { snap_id })
+            .collect();
+        invalidations.push(Invalidation::AllCharts);
+
+        invalidate(ctx, &invalidations, conn).await;
+    }
+
+    deleted_votes
+}
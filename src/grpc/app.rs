@@ -1,18 +1,20 @@
 use crate::{
+    cache::vote_summary_key,
     conn,
-    db::{Timeframe, VoteSummary},
-    grpc::populate_chart_data_with_names,
+    db::{RevisionFilter, Timeframe, VoteSummary},
+    grpc::{date_range, get_snap_names, populate_chart_data_with_names},
     proto::{
         app::{
             app_server::{App, AppServer},
+            get_rating_request::Revisions,
             GetBulkRatingsRequest, GetBulkRatingsResponse, GetRatingRequest, GetRatingResponse,
         },
         common::Rating as PbRating,
     },
-    ratings::{get_snap_name, Chart, Rating},
+    ratings::{Chart, Rating},
     Context,
 };
-use std::{error::Error, sync::Arc};
+use std::sync::Arc;
 use tonic::{Request, Response, Status};
 use tracing::error;
 
@@ -36,35 +38,55 @@ impl App for RatingService {
         &self,
         request: Request<GetRatingRequest>,
     ) -> Result<tonic::Response<GetRatingResponse>, Status> {
-        let GetRatingRequest { snap_id } = request.into_inner();
+        let GetRatingRequest {
+            snap_id,
+            revisions,
+            start,
+            end,
+        } = request.into_inner();
         if snap_id.is_empty() {
             return Err(Status::invalid_argument("snap id"));
         }
 
-        match VoteSummary::get_by_snap_id(&snap_id, conn!()).await {
+        let revisions = match revisions {
+            None => RevisionFilter::All,
+            Some(Revisions::SnapRevision(r)) if r > 0 => RevisionFilter::Exact(r as u32),
+            Some(Revisions::LatestRevisions(n)) if n > 0 => RevisionFilter::Latest(n),
+            Some(Revisions::SnapRevision(_)) => {
+                return Err(Status::invalid_argument("snap revision must be positive"))
+            }
+            Some(Revisions::LatestRevisions(_)) => {
+                return Err(Status::invalid_argument(
+                    "latest revisions must be positive",
+                ))
+            }
+        };
+
+        let range = date_range(start, end).map_err(Status::invalid_argument)?;
+
+        let conn = conn!();
+
+        let summary = self
+            .ctx
+            .caches
+            .vote_summaries
+            .get_or_try_insert_with(vote_summary_key(&snap_id, revisions, range), || {
+                VoteSummary::get_by_snap_id(&snap_id, revisions, range, self.ctx.vote_decay, conn)
+            })
+            .await;
+
+        match summary {
             Ok(votes) => {
                 let Rating {
                     snap_id,
                     total_votes,
                     ratings_band,
-                } = Rating::from(votes);
+                } = Rating::new(votes, &self.ctx.ranking_strategy, &self.ctx.band_thresholds);
 
-                let snap_name = get_snap_name(
-                    &snap_id,
-                    &self.ctx.config.snapcraft_io_uri,
-                    &self.ctx.http_client,
-                )
-                .await
-                .map_err(|e| {
-                    let mut err = &e as &dyn Error;
-                    let mut error = format!("{err}");
-                    while let Some(src) = err.source() {
-                        error.push_str(&format!("\n\nCaused by: {src}"));
-                        err = src;
-                    }
-                    error!(%error, "unable to fetch snap name");
-                    Status::unknown("Internal server error")
-                })?;
+                let snap_name = get_snap_names(&self.ctx, [snap_id.clone()], conn)
+                    .await?
+                    .remove(&snap_id)
+                    .unwrap_or_default();
 
                 Ok(Response::new(GetRatingResponse {
                     rating: Some(PbRating {
@@ -102,16 +124,19 @@ impl App for RatingService {
 
         const TIMEFRAME: Timeframe = Timeframe::Month;
 
-        let vote_summaries = VoteSummary::get_by_snap_ids(&snap_ids, TIMEFRAME, conn!())
-            .await
-            .map_err(|e| {
-                error!("Error calling get_by_snap_ids: {:?}", e);
-                Status::unknown("Internal server error")
-            })?;
+        let vote_summaries =
+            VoteSummary::get_by_snap_ids(&snap_ids, TIMEFRAME, self.ctx.vote_decay, conn!())
+                .await
+                .map_err(|e| {
+                    error!("Error calling get_by_snap_ids: {:?}", e);
+                    Status::unknown("Internal server error")
+                })?;
 
         let chart = Chart::new(
             TIMEFRAME,
-            vote_summaries.into_iter().map(Into::into).collect(),
+            vote_summaries,
+            &self.ctx.ranking_strategy,
+            &self.ctx.band_thresholds,
         );
 
         let ratings = populate_chart_data_with_names(&self.ctx, chart.data).await?;
@@ -1,22 +1,26 @@
 use crate::{
+    cache::chart_key,
     conn,
-    db::{Category, Timeframe, VoteSummary},
-    grpc::populate_chart_data_with_names,
+    db::{Category, DateRange, Timeframe, VoteSummary},
+    grpc::{date_range, populate_chart_data_with_names},
     proto::{
         chart::{
             chart_server::{self, ChartServer},
-            GetChartRequest, GetChartResponse,
+            Category as PbCategory, CategoryInfo, GetChartRequest, GetChartResponse,
+            ListCategoriesRequest, ListCategoriesResponse,
         },
         common::{Rating as PbRating, RatingsBand as PbRatingsBand},
     },
     ratings::{Chart, Rating, RatingsBand},
     Context,
 };
-use cached::proc_macro::cached;
 use std::sync::Arc;
 use tonic::{Request, Response, Status};
 use tracing::error;
 
+/// The number of chart entries returned when the client does not specify a page size
+const DEFAULT_CHART_PAGE_SIZE: u32 = 20;
+
 #[derive(Clone)]
 pub struct ChartService {
     ctx: Arc<Context>,
@@ -37,18 +41,64 @@ impl chart_server::Chart for ChartService {
         let GetChartRequest {
             timeframe,
             category,
+            category_slug,
+            page_size,
+            page_token,
+            start,
+            end,
         } = request.into_inner();
 
-        let category = match category {
-            Some(c) => Some(
-                Category::from_repr(c).ok_or(Status::invalid_argument("invalid category value"))?,
+        let page_size = match page_size {
+            0 => DEFAULT_CHART_PAGE_SIZE,
+            n => n,
+        }
+        .min(self.ctx.config.chart_max_page_size);
+
+        let offset = if page_token.is_empty() {
+            0
+        } else {
+            page_token
+                .parse()
+                .map_err(|_| Status::invalid_argument("invalid page token"))?
+        };
+
+        let category = match (category, category_slug) {
+            (Some(_), Some(_)) => {
+                return Err(Status::invalid_argument(
+                    "only one of category and category slug may be set",
+                ))
+            }
+            (Some(id), None) => Some(
+                Category::get_by_id(id, conn!())
+                    .await?
+                    .ok_or(Status::invalid_argument("invalid category value"))?,
             ),
-            None => None,
+            (None, Some(slug)) => Some(
+                Category::get_by_slug(&slug, conn!())
+                    .await?
+                    .ok_or(Status::invalid_argument("unknown category slug"))?,
+            ),
+            (None, None) => None,
         };
 
         let timeframe = Timeframe::from_repr(timeframe).unwrap_or(Timeframe::Unspecified);
 
-        let chart = get_chart_cached(category, timeframe).await;
+        let range = date_range(start, end).map_err(Status::invalid_argument)?;
+        if range.is_some() && timeframe != Timeframe::Unspecified {
+            return Err(Status::invalid_argument(
+                "a timeframe cannot be combined with a start and end",
+            ));
+        }
+
+        let category_id = category.as_ref().map(|c| c.id);
+        let chart = self
+            .ctx
+            .caches
+            .charts
+            .get_or_try_insert_with(chart_key(category_id, timeframe, range), || {
+                get_chart(&self.ctx, category_id, timeframe, range)
+            })
+            .await;
 
         match chart {
             Ok(chart) if chart.data.is_empty() => {
@@ -56,13 +106,19 @@ impl chart_server::Chart for ChartService {
             }
 
             Ok(chart) => {
+                let page = chart.page(offset, page_size as usize);
                 let ordered_chart_data =
-                    populate_chart_data_with_names(&self.ctx, chart.data).await?;
+                    populate_chart_data_with_names(&self.ctx, page.data).await?;
 
                 let payload = GetChartResponse {
                     timeframe: timeframe as i32,
-                    category: category.map(|c| c as i32),
+                    category: category
+                        .as_ref()
+                        .and_then(|c| PbCategory::try_from(c.id).ok())
+                        .map(|c| c as i32),
+                    category_slug: category.map(|c| c.slug),
                     ordered_chart_data,
+                    next_page_token: page.next.map(|n| n.to_string()).unwrap_or_default(),
                 };
 
                 Ok(Response::new(payload))
@@ -74,22 +130,52 @@ impl chart_server::Chart for ChartService {
             }
         }
     }
+
+    async fn list_categories(
+        &self,
+        _request: Request<ListCategoriesRequest>,
+    ) -> Result<Response<ListCategoriesResponse>, Status> {
+        match Category::get_all(conn!()).await {
+            Ok(categories) => {
+                let categories = categories
+                    .into_iter()
+                    .map(|Category { slug, name, .. }| CategoryInfo { slug, name })
+                    .collect();
+
+                Ok(Response::new(ListCategoriesResponse { categories }))
+            }
+
+            Err(e) => {
+                error!("unable to list categories: {e}");
+                Err(Status::unknown("Internal server error"))
+            }
+        }
+    }
 }
 
-#[cfg_attr(not(feature = "skip_cache"), cached(
-    time = 86400, // 24 hours
-    sync_writes = true,
-    key = "String",
-    convert = r##"{format!("{:?}{:?}", category, timeframe)}"##,
-    result = true,
-))]
-async fn get_chart_cached(
-    category: Option<Category>,
+async fn get_chart(
+    ctx: &Context,
+    category_id: Option<i32>,
     timeframe: Timeframe,
+    range: Option<DateRange>,
 ) -> Result<Chart, crate::db::Error> {
-    let summaries = VoteSummary::get_for_timeframe(timeframe, category, conn!()).await?;
+    let conn = conn!();
+    let (strategy, thresholds, decay) =
+        (&ctx.ranking_strategy, &ctx.band_thresholds, ctx.vote_decay);
+
+    if let Some(range) = range {
+        let summaries = VoteSummary::get_for_range(range, category_id, decay, conn).await?;
+        return Ok(Chart::new(timeframe, summaries, strategy, thresholds));
+    }
+
+    if timeframe == Timeframe::Trending {
+        let summaries = VoteSummary::get_trending(category_id, decay, conn).await?;
+        return Ok(Chart::new_trending(summaries, strategy, thresholds));
+    }
+
+    let summaries = VoteSummary::get_for_timeframe(timeframe, category_id, decay, conn).await?;
 
-    Ok(Chart::new(timeframe, summaries))
+    Ok(Chart::new(timeframe, summaries, strategy, thresholds))
 }
 
 impl From<PbRating> for Rating {
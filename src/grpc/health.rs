@@ -0,0 +1,52 @@
+//! Reporting the health of the service through the standard `grpc.health.v1` service
+use crate::{
+    db::check_db_conn,
+    grpc::{admin::AdminService, app::RatingService, charts::ChartService, user::UserService},
+    proto::{
+        admin::admin_server::AdminServer, app::app_server::AppServer,
+        chart::chart_server::ChartServer, user::user_server::UserServer,
+    },
+};
+use std::time::Duration;
+use tonic::server::NamedService;
+use tonic_health::{server::HealthReporter, ServingStatus};
+use tracing::{info, warn};
+
+/// How often we check that the DB is reachable
+const DB_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The services whose serving status follows the health of the DB, the empty string being the
+/// overall status of the server.
+const SERVICE_NAMES: [&str; 5] = [
+    "",
+    <AdminServer<AdminService> as NamedService>::NAME,
+    <AppServer<RatingService> as NamedService>::NAME,
+    <ChartServer<ChartService> as NamedService>::NAME,
+    <UserServer<UserService> as NamedService>::NAME,
+];
+
+/// Periodically checks the DB connection and updates the serving status of our services to match.
+pub(crate) async fn report_db_health(mut reporter: HealthReporter) {
+    let mut interval = tokio::time::interval(DB_HEALTH_CHECK_INTERVAL);
+    let mut last_status = None;
+
+    loop {
+        interval.tick().await;
+
+        let status = match check_db_conn().await {
+            Ok(()) => ServingStatus::Serving,
+            Err(e) => {
+                warn!("DB health check failed: {e}");
+                ServingStatus::NotServing
+            }
+        };
+
+        if last_status != Some(status) {
+            info!(?status, "updating serving status");
+            for name in SERVICE_NAMES {
+                reporter.set_service_status(name, status).await;
+            }
+            last_status = Some(status);
+        }
+    }
+}
@@ -1,22 +1,32 @@
 use crate::{
-    db,
-    jwt::JwtVerifier,
+    cache::{invalidate, listen_for_invalidations, Invalidation},
+    conn,
+    db::{self, categories_for_snap, DateRange},
+    metrics::MetricsLayer,
     middleware::AuthLayer,
-    proto::common::{ChartData as PbChartData, Rating as PbRating},
-    ratings::{get_snap_name, ChartData, Rating},
+    proto::{
+        common::{ChartData as PbChartData, Rating as PbRating},
+        FILE_DESCRIPTOR_SET,
+    },
+    ratings::{self, refresh_categories_periodically, ChartData, Rating},
     Context,
 };
-use futures::future::try_join_all;
-use std::{error::Error, fs::read_to_string, net::SocketAddr, sync::Arc};
+use prost_types::Timestamp;
+use sqlx::PgConnection;
+use std::{collections::HashMap, error::Error, fs::read_to_string, net::SocketAddr, sync::Arc};
+use time::OffsetDateTime;
 use tonic::{
     transport::{Identity, Server, ServerTlsConfig},
     Status,
 };
-use tracing::{error, warn};
+use tracing::{error, info, warn};
+mod admin;
 mod app;
 mod charts;
+mod health;
 mod user;
 
+use admin::AdminService;
 use app::RatingService;
 use charts::ChartService;
 use user::UserService;
@@ -27,8 +37,7 @@ impl From<db::Error> for Status {
     }
 }
 
-pub async fn run_server(ctx: Context) -> Result<(), Box<dyn std::error::Error>> {
-    let verifier = JwtVerifier::from_secret(&ctx.config.jwt_secret)?;
+pub async fn run_server(ctx: Arc<Context>) -> Result<(), Box<dyn std::error::Error>> {
     let addr: SocketAddr = ctx.config.socket().parse()?;
 
     let keychain_path = ctx.config.tls_keychain_path.clone();
@@ -50,10 +59,34 @@ pub async fn run_server(ctx: Context) -> Result<(), Box<dyn std::error::Error>>
         }
     };
 
-    let ctx = Arc::new(ctx);
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .build_v1()?;
+    let reflection_service_v1alpha = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .build_v1alpha()?;
+
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+    tokio::spawn(health::report_db_health(health_reporter));
+    tokio::spawn(refresh_categories_periodically(ctx.clone()));
+    tokio::spawn(listen_for_invalidations(ctx.clone()));
+
+    let admin_credentials = ctx.config.admin_credentials();
+    if admin_credentials.is_none() {
+        info!("no admin credentials are configured, the admin service requires an admin token");
+    }
 
     builder
-        .layer(AuthLayer::new(verifier))
+        .layer(MetricsLayer)
+        .layer(AuthLayer::new(
+            ctx.jwt_verifier.clone(),
+            admin_credentials,
+            ctx.revocations.clone(),
+        ))
+        .add_service(AdminService::new_server(ctx.clone()))
+        .add_service(health_service)
+        .add_service(reflection_service)
+        .add_service(reflection_service_v1alpha)
         .add_service(RatingService::new_server(ctx.clone()))
         .add_service(ChartService::new_server(ctx.clone()))
         .add_service(UserService::new_server(ctx.clone()))
@@ -67,12 +100,25 @@ pub(crate) async fn populate_chart_data_with_names(
     ctx: &Arc<Context>,
     chart_data_vec: Vec<ChartData>,
 ) -> Result<Vec<PbChartData>, Status> {
-    try_join_all(chart_data_vec.into_iter().map(|chart_data| async {
-        let snap_name = get_snap_name(
-            &chart_data.rating.snap_id,
-            &ctx.config.snapcraft_io_uri,
-            &ctx.http_client,
-        )
+    let snap_ids = chart_data_vec.iter().map(|c| c.rating.snap_id.clone());
+    let snap_names = get_snap_names(ctx, snap_ids, conn!()).await?;
+
+    Ok(chart_data_vec
+        .into_iter()
+        .map(|chart_data| {
+            let snap_name = snap_names[&chart_data.rating.snap_id].clone();
+            PbChartData::from_chart_data_and_snap_name(chart_data, snap_name)
+        })
+        .collect())
+}
+
+/// Resolves the names for a batch of snaps, logging the full error chain if this fails.
+pub(crate) async fn get_snap_names(
+    ctx: &Context,
+    snap_ids: impl IntoIterator<Item = String>,
+    conn: &mut PgConnection,
+) -> Result<HashMap<String, String>, Status> {
+    ratings::get_snap_names(snap_ids, ctx, conn)
         .await
         .map_err(|e| {
             let mut err = &e as &dyn Error;
@@ -81,15 +127,55 @@ pub(crate) async fn populate_chart_data_with_names(
                 error_chain.push_str(&format!("\nCaused by: {src}"));
                 err = src;
             }
-            error!(error=%error_chain, "unable to fetch snap name");
+            error!(error=%error_chain, "unable to fetch snap names");
             Status::unknown("Internal server error")
-        })?;
+        })
+}
 
-        Ok(PbChartData::from_chart_data_and_snap_name(
-            chart_data, snap_name,
-        ))
-    }))
-    .await
+/// Invalidates the cached rating for a snap along with the cached charts that it may appear in,
+/// on every replica, so that changes to its votes are visible straight away.
+pub(crate) async fn invalidate_cached_ratings(
+    ctx: &Context,
+    snap_id: &str,
+    conn: &mut PgConnection,
+) {
+    let charts = match categories_for_snap(snap_id, conn).await {
+        Ok(categories) => Invalidation::Charts {
+            category_ids: categories.iter().map(|c| c.id).collect(),
+        },
+
+        Err(e) => {
+            warn!(%snap_id, "unable to look up snap categories, flushing all charts: {e}");
+            Invalidation::AllCharts
+        }
+    };
+
+    let snap = Invalidation::Snap {
+        snap_id: snap_id.to_string(),
+    };
+
+    invalidate(ctx, &[snap, charts], conn).await;
+}
+
+/// Converts the start and end timestamps from a request into a [DateRange], returning an error
+/// message suitable for the client if they are invalid. Both or neither must be set.
+pub(crate) fn date_range(
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+) -> Result<Option<DateRange>, String> {
+    let (start, end) = match (start, end) {
+        (Some(start), Some(end)) => (start, end),
+        (None, None) => return Ok(None),
+        _ => return Err("both start and end must be set, or neither".to_string()),
+    };
+
+    DateRange::new(to_datetime(start)?, to_datetime(end)?).map(Some)
+}
+
+fn to_datetime(ts: Timestamp) -> Result<OffsetDateTime, String> {
+    let nanos = ts.seconds as i128 * 1_000_000_000 + ts.nanos as i128;
+
+    OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(|_| format!("invalid timestamp: {ts}"))
 }
 
 impl PbChartData {
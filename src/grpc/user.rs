@@ -1,17 +1,22 @@
 use crate::{
+    cache::{invalidate, Invalidation},
     conn,
-    db::{User, Vote},
-    jwt::Claims,
+    db::{
+        self, create_refresh_token, redeem_refresh_token, TokenRevocation, User, Vote, VoteCursor,
+    },
+    grpc::{get_snap_names, invalidate_cached_ratings},
+    jwt::{hash_refresh_token, new_refresh_token, Claims},
     proto::user::{
         user_server::{self, UserServer},
         AuthenticateRequest, AuthenticateResponse, GetSnapVotesRequest, GetSnapVotesResponse,
-        Vote as PbVote, VoteRequest,
+        ListMyVotesRequest, ListMyVotesResponse, RefreshTokenRequest, RetractVoteRequest,
+        RevokeRefreshTokenRequest, Vote as PbVote, VoteRequest,
     },
-    ratings::{get_snap_name, update_categories, Error},
+    ratings::update_categories,
     Context,
 };
-use futures::future::try_join_all;
-use std::sync::Arc;
+use sqlx::{Connection, PgConnection};
+use std::{collections::HashSet, sync::Arc};
 use time::OffsetDateTime;
 use tonic::{Request, Response, Status};
 use tracing::{error, warn};
@@ -19,6 +24,12 @@ use tracing::{error, warn};
 /// The length we expect a client hash to be, in bytes
 pub const EXPECTED_CLIENT_HASH_LENGTH: usize = 64;
 
+/// The number of votes returned by ListMyVotes if the client does not specify a page size
+const DEFAULT_VOTES_PAGE_SIZE: u32 = 50;
+
+/// The maximum number of votes returned by a single ListMyVotes call
+const MAX_VOTES_PAGE_SIZE: u32 = 250;
+
 /// An empty struct used to construct a [`UserServer`]
 #[derive(Clone)]
 pub struct UserService {
@@ -29,6 +40,34 @@ impl UserService {
     pub fn new_server(ctx: Arc<Context>) -> UserServer<UserService> {
         UserServer::new(Self { ctx })
     }
+
+    /// Issues a new access token along with a refresh token that can later be exchanged for
+    /// another access token.
+    async fn issue_tokens(
+        &self,
+        user: User,
+        conn: &mut PgConnection,
+    ) -> Result<AuthenticateResponse, Status> {
+        let token = self
+            .ctx
+            .jwt_encoder
+            .encode(user.client_hash)
+            .map_err(|_| Status::internal("internal error"))?;
+
+        let (refresh_token, token_hash) = new_refresh_token();
+        let expires = OffsetDateTime::now_utc() + self.ctx.config.refresh_token_lifetime();
+        create_refresh_token(&token_hash, user.id, expires, conn)
+            .await
+            .map_err(|e| {
+                error!("Error in create_refresh_token: {:?}", e);
+                Status::unknown("Internal server error")
+            })?;
+
+        Ok(AuthenticateResponse {
+            token,
+            refresh_token,
+        })
+    }
 }
 
 #[tonic::async_trait]
@@ -46,29 +85,102 @@ impl user_server::User for UserService {
             return Err(Status::invalid_argument(error));
         }
 
-        match User::create_or_seen(&id, conn!()).await {
-            Ok(user) => match self.ctx.jwt_encoder.encode(user.client_hash) {
-                Ok(token) => Ok(Response::new(AuthenticateResponse { token })),
-                Err(_) => Err(Status::internal("internal error")),
-            },
+        let conn = conn!();
+        check_not_banned(&id, conn).await?;
 
+        match User::create_or_seen(&id, conn).await {
+            Ok(user) => Ok(Response::new(self.issue_tokens(user, conn).await?)),
             Err(_error) => Err(Status::invalid_argument("id")),
         }
     }
 
+    async fn refresh_token(
+        &self,
+        request: Request<RefreshTokenRequest>,
+    ) -> Result<Response<AuthenticateResponse>, Status> {
+        let RefreshTokenRequest { refresh_token } = request.into_inner();
+        let conn = conn!();
+
+        let client_hash =
+            match redeem_refresh_token(&hash_refresh_token(&refresh_token), conn).await {
+                Ok(Some(client_hash)) => client_hash,
+                Ok(None) => return Err(Status::unauthenticated("invalid refresh token")),
+                Err(e) => {
+                    error!("Error in redeem_refresh_token: {:?}", e);
+                    return Err(Status::unknown("Internal server error"));
+                }
+            };
+
+        check_not_banned(&client_hash, conn).await?;
+
+        match User::create_or_seen(&client_hash, conn).await {
+            Ok(user) => Ok(Response::new(self.issue_tokens(user, conn).await?)),
+            Err(e) => {
+                error!("Error in create_or_seen: {:?}", e);
+                Err(Status::unknown("Internal server error"))
+            }
+        }
+    }
+
+    async fn revoke_refresh_token(
+        &self,
+        request: Request<RevokeRefreshTokenRequest>,
+    ) -> Result<Response<()>, Status> {
+        let RevokeRefreshTokenRequest { refresh_token } = request.into_inner();
+
+        // Revoking a token that is unknown or no longer valid is not an error so that clients
+        // can't use this to probe for valid tokens.
+        match redeem_refresh_token(&hash_refresh_token(&refresh_token), conn!()).await {
+            Ok(_) => Ok(Response::new(())),
+            Err(e) => {
+                error!("Error in redeem_refresh_token: {:?}", e);
+                Err(Status::unknown("Internal server error"))
+            }
+        }
+    }
+
     async fn delete(&self, mut request: Request<()>) -> Result<Response<()>, Status> {
         let Claims {
             sub: client_hash, ..
         } = claims(&mut request);
 
-        match User::delete_by_client_hash(&client_hash, conn!()).await {
-            Ok(_) => Ok(Response::new(())),
+        let conn = conn!();
+        let revocation = TokenRevocation::now(self.ctx.config.access_token_lifetime());
 
-            Err(e) => {
-                error!("Error in delete_user_by_client_hash: {:?}", e);
-                Err(Status::unknown("Internal server error"))
-            }
+        // Access tokens remain valid until they expire so any that have already been issued to
+        // the user need revoking. Their refresh tokens are removed along with the user. Everything
+        // is removed in one transaction so that a failure part way through leaves the user intact.
+        let res = async {
+            let mut tx = conn.begin().await?;
+            User::revoke_tokens(&client_hash, &revocation, &mut tx).await?;
+
+            // Deleting the user cascades to their votes, so remove them up front to find the
+            // snaps whose cached ratings need invalidating.
+            let snap_ids = Vote::delete_all_by_client_hash(&client_hash, &mut tx).await?;
+            User::delete_by_client_hash(&client_hash, &mut tx).await?;
+            tx.commit().await?;
+
+            Ok::<_, db::Error>(snap_ids)
+        };
+
+        let snap_ids = res.await.map_err(|e| {
+            error!("Error in delete: {:?}", e);
+            Status::unknown("Internal server error")
+        })?;
+
+        // Other replicas may repopulate their caches as soon as they are invalidated, so this
+        // waits until the votes are gone.
+        let revoked = Invalidation::TokensRevoked {
+            client_hash,
+            revocation,
+        };
+        invalidate(&self.ctx, &[revoked], conn).await;
+
+        for snap_id in snap_ids.into_iter().collect::<HashSet<_>>() {
+            invalidate_cached_ratings(&self.ctx, &snap_id, conn).await;
         }
+
+        Ok(Response::new(()))
     }
 
     async fn vote(&self, mut request: Request<VoteRequest>) -> Result<Response<()>, Status> {
@@ -80,6 +192,9 @@ impl user_server::User for UserService {
         } = request.into_inner();
         let conn = conn!();
 
+        // Tokens issued before a ban remain valid until they expire so we need to check here
+        check_not_banned(&sub, conn).await?;
+
         // Ignore but log warning, it's not fatal
         if let Err(e) = update_categories(&snap_id, &self.ctx, conn).await {
             warn!("unable to update categories for snap: {e}");
@@ -87,14 +202,21 @@ impl user_server::User for UserService {
 
         let vote = Vote {
             client_hash: sub,
-            snap_id,
+            snap_id: snap_id.clone(),
             snap_revision: snap_revision as u32,
             vote_up,
             timestamp: OffsetDateTime::now_utc(),
         };
 
         match vote.save_to_db(conn).await {
-            Ok(_) => Ok(Response::new(())),
+            // The user for a valid token may still have been removed, for example by an account
+            // deletion made before tokens were revoked on deletion
+            Ok(0) => Err(Status::unauthenticated("unknown user")),
+
+            Ok(_) => {
+                invalidate_cached_ratings(&self.ctx, &snap_id, conn).await;
+                Ok(Response::new(()))
+            }
 
             Err(e) => {
                 error!("Error in save_vote_to_db: {:?}", e);
@@ -103,6 +225,42 @@ impl user_server::User for UserService {
         }
     }
 
+    async fn retract_vote(
+        &self,
+        mut request: Request<RetractVoteRequest>,
+    ) -> Result<Response<()>, Status> {
+        let Claims {
+            sub: client_hash, ..
+        } = claims(&mut request);
+        let RetractVoteRequest {
+            snap_id,
+            snap_revision,
+        } = request.into_inner();
+
+        if snap_id.is_empty() {
+            return Err(Status::invalid_argument("snap id"));
+        }
+        if snap_revision <= 0 {
+            return Err(Status::invalid_argument("snap revision must be positive"));
+        }
+
+        let conn = conn!();
+
+        match Vote::delete(&client_hash, &snap_id, snap_revision as u32, conn).await {
+            Ok(0) => Err(Status::not_found("no vote found for snap revision")),
+
+            Ok(_) => {
+                invalidate_cached_ratings(&self.ctx, &snap_id, conn).await;
+                Ok(Response::new(()))
+            }
+
+            Err(e) => {
+                error!("Error in retract_vote: {:?}", e);
+                Err(Status::unknown("Internal server error"))
+            }
+        }
+    }
+
     async fn get_snap_votes(
         &self,
         mut request: Request<GetSnapVotesRequest>,
@@ -121,18 +279,16 @@ impl user_server::User for UserService {
 
         match Vote::get_all_by_client_hash(&client_hash, Some(snap_id), conn).await {
             Ok(votes) => {
-                let votes = try_join_all(votes.into_iter().map(|vote| async {
-                    let snap_name = get_snap_name(
-                        &vote.snap_id,
-                        &self.ctx.config.snapcraft_io_uri,
-                        &self.ctx.http_client,
-                    )
-                    .await?;
-
-                    Result::<PbVote, Error>::Ok(PbVote::from_vote_and_snap_name(vote, &snap_name))
-                }))
-                .await
-                .map_err(|_| Status::unknown("Internal server error"))?;
+                let snap_names =
+                    get_snap_names(&self.ctx, votes.iter().map(|v| v.snap_id.clone()), conn)
+                        .await?;
+                let votes = votes
+                    .into_iter()
+                    .map(|vote| {
+                        let snap_name = &snap_names[&vote.snap_id];
+                        PbVote::from_vote_and_snap_name(vote, snap_name)
+                    })
+                    .collect();
                 let payload = GetSnapVotesResponse { votes };
 
                 Ok(Response::new(payload))
@@ -144,6 +300,63 @@ impl user_server::User for UserService {
             }
         }
     }
+
+    async fn list_my_votes(
+        &self,
+        mut request: Request<ListMyVotesRequest>,
+    ) -> Result<Response<ListMyVotesResponse>, Status> {
+        let Claims {
+            sub: client_hash, ..
+        } = claims(&mut request);
+        let ListMyVotesRequest {
+            page_size,
+            page_token,
+        } = request.into_inner();
+
+        let page_size = match page_size {
+            0 => DEFAULT_VOTES_PAGE_SIZE,
+            n => n.min(MAX_VOTES_PAGE_SIZE),
+        };
+
+        let after = if page_token.is_empty() {
+            None
+        } else {
+            Some(
+                VoteCursor::decode(&page_token)
+                    .ok_or(Status::invalid_argument("invalid page token"))?,
+            )
+        };
+
+        let conn = conn!();
+
+        let page = Vote::get_page_by_client_hash(&client_hash, after, page_size, conn)
+            .await
+            .map_err(|e| {
+                error!("Error in get_page_by_client_hash: {:?}", e);
+                Status::unknown("Internal server error")
+            })?;
+
+        let snap_names = get_snap_names(
+            &self.ctx,
+            page.votes.iter().map(|v| v.snap_id.clone()),
+            conn,
+        )
+        .await?;
+
+        let votes = page
+            .votes
+            .into_iter()
+            .map(|vote| {
+                let snap_name = &snap_names[&vote.snap_id];
+                PbVote::from_vote_and_snap_name(vote, snap_name)
+            })
+            .collect();
+
+        Ok(Response::new(ListMyVotesResponse {
+            votes,
+            next_page_token: page.next.map(|c| c.encode()).unwrap_or_default(),
+        }))
+    }
 }
 
 impl PbVote {
@@ -164,6 +377,17 @@ impl PbVote {
 }
 
 #[inline]
+async fn check_not_banned(client_hash: &str, conn: &mut PgConnection) -> Result<(), Status> {
+    match User::is_banned(client_hash, conn).await {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(Status::permission_denied("client is banned")),
+        Err(e) => {
+            error!("Error in is_banned: {:?}", e);
+            Err(Status::unknown("Internal server error"))
+        }
+    }
+}
+
 fn claims<T>(request: &mut Request<T>) -> Claims {
     request
         .extensions_mut()
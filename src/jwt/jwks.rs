@@ -0,0 +1,28 @@
+//! Publishing the public halves of our asymmetric signing keys as a JWKS document so that our other
+//! backends can verify the tokens we issue.
+//!
+//! The document is served over HTTP from its own port rather than alongside the metrics, so that
+//! it is always available whenever tokens may be signed by an asymmetric key.
+use crate::Context;
+use axum::{extract::State, routing::get, Json, Router};
+use jsonwebtoken::jwk::JwkSet;
+use std::{error::Error, net::SocketAddr, sync::Arc};
+use tracing::info;
+
+async fn jwks_handler(State(ctx): State<Arc<Context>>) -> Json<JwkSet> {
+    Json(ctx.jwt_keys.jwks())
+}
+
+/// Serves the JWKS document at `/.well-known/jwks.json` over HTTP on the given port.
+pub async fn run_jwks_server(ctx: Arc<Context>, port: u16) -> Result<(), Box<dyn Error>> {
+    let addr: SocketAddr = format!("{}:{port}", ctx.config.host).parse()?;
+    let app = Router::new()
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .with_state(ctx);
+
+    info!(%addr, "starting jwks server");
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
@@ -0,0 +1,371 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::{rngs::OsRng, RngCore};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use time::{Duration, OffsetDateTime};
+use tonic::Status;
+use tracing::error;
+
+mod jwks;
+mod keys;
+
+pub use jwks::run_jwks_server;
+pub use keys::{JwtKey, JwtKeyMaterial, JwtKeySet};
+
+/// The number of random bytes in a refresh token
+const REFRESH_TOKEN_BYTES: usize = 32;
+
+/// Errors that can happen while encoding and signing tokens with JWT.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("jwt: error decoding secret: {0}")]
+    DecodeSecretError(#[from] jsonwebtoken::errors::Error),
+
+    #[error(transparent)]
+    Envy(#[from] envy::Error),
+
+    #[error("jwt: an error occurred, but the reason was erased for security reasons")]
+    Erased,
+
+    #[error("jwt: invalid shape")]
+    InvalidShape,
+
+    #[error("jwt: invalid key set: {0}")]
+    InvalidKeySet(String),
+
+    #[error("jwt: invalid authz token")]
+    InvalidHeader,
+
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    TonicStatus(#[from] Box<Status>),
+}
+
+impl From<Error> for Status {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::DecodeSecretError(_) => Status::unauthenticated("invalid JWT token"),
+            Error::InvalidHeader => Status::unauthenticated("invalid authz header"),
+            Error::TonicStatus(status) => *status,
+            _ => Status::internal("Internal Server Error"),
+        }
+    }
+}
+
+/// A role granting access to RPCs beyond those open to every client.
+///
+/// Clients authenticating with the service are never granted roles, tokens carrying roles are
+/// issued by the trusted services holding one of our signing keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Operators of the service, who may call any RPC
+    Admin,
+    /// Snap publishers. Nothing limits a publisher token to the snaps they publish, so no RPC
+    /// currently needs this role and it grants no more access than a client token.
+    Publisher,
+}
+
+/// Information representating a claim on a specific subject at a specific time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Claims {
+    /// The subject
+    pub sub: String,
+    /// The expiration time
+    pub exp: usize,
+    /// The time the token was issued, which is zero for tokens issued before it was recorded.
+    /// This has sub-second precision so that tokens issued straight after a revocation can be
+    /// told apart from those it covers.
+    #[serde(default)]
+    pub iat: f64,
+    /// The roles granted to the subject on top of the access every client has
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<Role>,
+}
+
+impl Claims {
+    /// Creates a new claim for the subject given by `sub` which expires after `lifetime`.
+    pub fn new(sub: String, lifetime: Duration) -> Self {
+        Self::with_roles(sub, Vec::new(), lifetime)
+    }
+
+    /// Creates a new claim for the subject given by `sub` with the given roles, which expires
+    /// after `lifetime`.
+    pub fn with_roles(sub: String, roles: Vec<Role>, lifetime: Duration) -> Self {
+        let now = OffsetDateTime::now_utc();
+        let iat = now.unix_timestamp_nanos() as f64 / 1e9;
+        let exp = (now + lifetime).unix_timestamp() as usize;
+
+        Self {
+            sub,
+            exp,
+            iat,
+            roles,
+        }
+    }
+
+    /// The time the token was issued.
+    pub fn issued_at(&self) -> OffsetDateTime {
+        let seconds = self.iat.trunc();
+        let nanos = ((self.iat - seconds) * 1e9).round() as i64;
+
+        OffsetDateTime::from_unix_timestamp(seconds as i64)
+            .map(|t| t + Duration::nanoseconds(nanos))
+            .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+    }
+}
+
+pub struct JwtEncoder {
+    encoding_key: EncodingKey,
+    algorithm: Algorithm,
+    kid: Option<String>,
+    lifetime: Duration,
+}
+
+impl JwtEncoder {
+    /// Creates a new encoder that signs tokens with the active key from the given [JwtKeySet],
+    /// issuing tokens that expire after `lifetime`.
+    pub fn new(keys: &JwtKeySet, lifetime: Duration) -> Result<JwtEncoder, Error> {
+        let JwtKey { kid, material } = keys.active();
+
+        Ok(Self {
+            encoding_key: material.encoding_key()?,
+            algorithm: material.algorithm(),
+            kid: kid.clone(),
+            lifetime,
+        })
+    }
+
+    pub fn encode(&self, sub: String) -> Result<String, Error> {
+        self.encode_with_roles(sub, Vec::new())
+    }
+
+    /// Encodes a token for `sub` which grants the given roles.
+    pub fn encode_with_roles(&self, sub: String, roles: Vec<Role>) -> Result<String, Error> {
+        let claims = Claims::with_roles(sub, roles, self.lifetime);
+        let header = Header {
+            kid: self.kid.clone(),
+            ..Header::new(self.algorithm)
+        };
+
+        match jsonwebtoken::encode(&header, &claims, &self.encoding_key) {
+            Ok(s) => Ok(s),
+            Err(e) => {
+                error!("unable to encode jwt: {e}");
+                Err(Error::Erased)
+            }
+        }
+    }
+}
+
+/// Verifies tokens signed by any of the keys in a [JwtKeySet], using the `kid` header of each
+/// token to select the key.
+#[derive(Clone)]
+pub struct JwtVerifier {
+    decoding_keys: HashMap<Option<String>, (DecodingKey, Validation)>,
+}
+
+impl JwtVerifier {
+    /// Creates a new verifier from the given secret, which accepts tokens without a `kid`.
+    pub fn from_secret(secret: &SecretString) -> Result<Self, Error> {
+        let keys = JwtKeySet::new(vec![JwtKey::hmac(None, secret.clone())], None)?;
+
+        Self::new(&keys)
+    }
+
+    /// Creates a new verifier accepting tokens signed by any of the keys in the given set.
+    pub fn new(keys: &JwtKeySet) -> Result<Self, Error> {
+        let decoding_keys = keys
+            .keys()
+            .iter()
+            .map(|JwtKey { kid, material }| {
+                let validation = Validation::new(material.algorithm());
+                Ok((kid.clone(), (material.decoding_key()?, validation)))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self { decoding_keys })
+    }
+
+    pub fn decode(&self, token: &str) -> Result<Claims, Error> {
+        let header = jsonwebtoken::decode_header(token).map_err(|e| {
+            error!("{e:?}");
+            Error::InvalidShape
+        })?;
+
+        let Some((decoding_key, validation)) = self.decoding_keys.get(&header.kid) else {
+            error!(kid = ?header.kid, "jwt signed with an unknown key");
+            return Err(Error::InvalidShape);
+        };
+
+        jsonwebtoken::decode::<Claims>(token, decoding_key, validation)
+            .map(|t| t.claims)
+            .map_err(|e| {
+                error!("{e:?}");
+                Error::InvalidShape
+            })
+    }
+}
+
+/// Generates a new random refresh token, returning the token for the client along with the hash
+/// of it that we store.
+pub fn new_refresh_token() -> (String, String) {
+    let mut bytes = [0; REFRESH_TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let token = URL_SAFE_NO_PAD.encode(bytes);
+    let hash = hash_refresh_token(&token);
+
+    (token, hash)
+}
+
+/// The hash of a refresh token, which is all that we store so that the tokens themselves can not
+/// be recovered from the DB.
+pub fn hash_refresh_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{keys::test_keys::*, *};
+
+    fn key(kid: &str, secret: &str) -> JwtKey {
+        JwtKey::hmac(Some(kid), SecretString::new(secret.to_string()))
+    }
+
+    #[test]
+    fn tokens_signed_with_previous_keys_are_accepted_after_rotation() {
+        let old = key("old", "deadbeef");
+        let new = key("new", "c2VjcmV0");
+
+        let before = JwtKeySet::new(vec![old.clone()], Some("old")).unwrap();
+        let token = JwtEncoder::new(&before, Duration::hours(1))
+            .unwrap()
+            .encode("sub".to_string())
+            .unwrap();
+
+        let after = JwtKeySet::new(vec![old, new.clone()], Some("new")).unwrap();
+        let verifier = JwtVerifier::new(&after).unwrap();
+        assert_eq!(verifier.decode(&token).unwrap().sub, "sub");
+
+        let new_token = JwtEncoder::new(&after, Duration::hours(1))
+            .unwrap()
+            .encode("sub".to_string())
+            .unwrap();
+        let header = jsonwebtoken::decode_header(&new_token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("new"));
+
+        let removed = JwtKeySet::new(vec![new], Some("new")).unwrap();
+        let verifier = JwtVerifier::new(&removed).unwrap();
+        assert!(verifier.decode(&token).is_err(), "old key has been removed");
+        assert!(verifier.decode(&new_token).is_ok());
+    }
+
+    #[test]
+    fn issue_times_survive_encoding_with_sub_second_precision() {
+        let keys = JwtKeySet::new(vec![key("a", "deadbeef")], Some("a")).unwrap();
+        let token = JwtEncoder::new(&keys, Duration::hours(1))
+            .unwrap()
+            .encode("sub".to_string())
+            .unwrap();
+        let issued_at = JwtVerifier::new(&keys)
+            .unwrap()
+            .decode(&token)
+            .unwrap()
+            .issued_at();
+
+        let now = OffsetDateTime::now_utc();
+        assert!(issued_at <= now && now - issued_at < Duration::seconds(1));
+
+        let claims: Claims =
+            serde_json::from_str(r#"{"sub": "sub", "exp": 1, "iat": 1700000000.25}"#).unwrap();
+        assert_eq!(
+            claims.issued_at(),
+            OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap()
+                + Duration::milliseconds(250)
+        );
+    }
+
+    #[test]
+    fn tokens_without_a_kid_use_the_jwt_secret() {
+        let secret = SecretString::new("deadbeef".to_string());
+        let keys = JwtKeySet::new(
+            vec![JwtKey::hmac(None, secret.clone()), key("other", "c2VjcmV0")],
+            None,
+        )
+        .unwrap();
+        let token = JwtEncoder::new(&keys, Duration::hours(1))
+            .unwrap()
+            .encode("sub".to_string())
+            .unwrap();
+
+        assert!(JwtVerifier::from_secret(&secret)
+            .unwrap()
+            .decode(&token)
+            .is_ok());
+    }
+
+    #[test]
+    fn asymmetric_tokens_can_be_verified_with_the_jwks() {
+        let keys = JwtKeySet::new(
+            vec![
+                JwtKey {
+                    kid: Some("rsa".to_string()),
+                    material: JwtKeyMaterial::from_pem(RSA_PEM).unwrap(),
+                },
+                JwtKey {
+                    kid: Some("ed25519".to_string()),
+                    material: JwtKeyMaterial::from_pem(&ed25519_pem()).unwrap(),
+                },
+            ],
+            Some("rsa"),
+        )
+        .unwrap();
+        let verifier = JwtVerifier::new(&keys).unwrap();
+        let jwks = keys.jwks();
+
+        for (kid, alg) in [("rsa", Algorithm::RS256), ("ed25519", Algorithm::EdDSA)] {
+            let keys = JwtKeySet::new(keys.keys().to_vec(), Some(kid)).unwrap();
+            let token = JwtEncoder::new(&keys, Duration::hours(1))
+                .unwrap()
+                .encode("sub".to_string())
+                .unwrap();
+
+            let header = jsonwebtoken::decode_header(&token).unwrap();
+            assert_eq!(header.alg, alg);
+            assert_eq!(verifier.decode(&token).unwrap().sub, "sub");
+
+            let jwk = jwks.find(kid).unwrap();
+            let decoding_key = DecodingKey::from_jwk(jwk).unwrap();
+            let claims =
+                jsonwebtoken::decode::<Claims>(&token, &decoding_key, &Validation::new(alg))
+                    .unwrap()
+                    .claims;
+            assert_eq!(claims.sub, "sub");
+        }
+    }
+
+    #[test]
+    fn tokens_must_use_the_algorithm_of_their_key() {
+        let rsa = JwtKey {
+            kid: Some("rsa".to_string()),
+            material: JwtKeyMaterial::from_pem(RSA_PEM).unwrap(),
+        };
+        let keys = JwtKeySet::new(vec![rsa], Some("rsa")).unwrap();
+        let verifier = JwtVerifier::new(&keys).unwrap();
+
+        let header = Header {
+            kid: Some("rsa".to_string()),
+            ..Header::new(Algorithm::HS256)
+        };
+        let claims = Claims::new("sub".to_string(), Duration::hours(1));
+        let encoding_key = EncodingKey::from_base64_secret("deadbeef").unwrap();
+        let token = jsonwebtoken::encode(&header, &claims, &encoding_key).unwrap();
+
+        assert!(verifier.decode(&token).is_err());
+    }
+}
@@ -1,8 +1,10 @@
+pub mod cache;
 pub mod config;
 pub mod context;
 pub mod db;
 pub mod grpc;
 pub mod jwt;
+pub mod metrics;
 pub mod middleware;
 pub mod proto;
 pub mod ratings;
@@ -1,6 +1,9 @@
-use ratings::{db::check_db_conn, grpc::run_server, Config, Context};
-use std::io::stdout;
-use tracing::{info, subscriber::set_global_default};
+use ratings::{
+    db::check_db_conn, grpc::run_server, jwt::run_jwks_server, metrics::run_metrics_server, Config,
+    Context,
+};
+use std::{io::stdout, sync::Arc};
+use tracing::{error, info, subscriber::set_global_default};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 #[tokio::main]
@@ -20,11 +23,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     set_global_default(subscriber).expect("unable to set a global tracing subscriber");
 
     info!("loading application context");
-    let ctx = Context::new(Config::load()?)?;
+    let ctx = Arc::new(Context::new(Config::load()?)?);
 
     info!("checking DB connectivity");
     check_db_conn().await?; // Ensure that the migrations run before server start
 
+    if let Some(port) = ctx.config.metrics_port {
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_metrics_server(ctx, port).await {
+                error!("metrics server failed: {e}");
+            }
+        });
+    }
+
+    let jwks_ctx = ctx.clone();
+    tokio::spawn(async move {
+        let port = jwks_ctx.config.jwks_port;
+        if let Err(e) = run_jwks_server(jwks_ctx, port).await {
+            error!("jwks server failed: {e}");
+        }
+    });
+
     info!("starting server");
     run_server(ctx).await?;
 
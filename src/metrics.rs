@@ -0,0 +1,324 @@
+//! Prometheus metrics for the service along with a custom Tower [Layer] for recording per-RPC
+//! request counts and latencies.
+//!
+//! Metrics are served in the Prometheus text format from a separate HTTP server so that they
+//! are not exposed through the public gRPC port.
+use crate::{db, middleware::policy_for, Context as AppContext};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Router};
+use http::{Request, Response};
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge_vec,
+    Encoder, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use std::{
+    error::Error,
+    future::Future,
+    mem::replace,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, LazyLock},
+    task::{Context, Poll},
+    time::Instant,
+};
+use tonic::{Code, Status};
+use tower::{Layer, Service};
+use tracing::{error, info};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// The number of gRPC requests handled, by method and status code
+static GRPC_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "ratings_grpc_requests_total",
+        "The number of gRPC requests handled",
+        &["method", "code"]
+    )
+    .unwrap()
+});
+
+/// The time taken to handle gRPC requests, by method
+static GRPC_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "ratings_grpc_request_duration_seconds",
+        "The time taken to handle gRPC requests",
+        &["method"]
+    )
+    .unwrap()
+});
+
+/// The number of requests rejected by the auth middleware, by reason
+static AUTH_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "ratings_auth_failures_total",
+        "The number of requests rejected by the auth middleware",
+        &["reason"]
+    )
+    .unwrap()
+});
+
+/// Cache hits and misses since startup, by cache and result. These are gauges as they are copied
+/// from the counts kept by the caches themselves rather than incremented here.
+static CACHE_REQUESTS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "ratings_cache_requests",
+        "The number of cache lookups since startup",
+        &["cache", "result"]
+    )
+    .unwrap()
+});
+
+/// The time taken for requests to snapcraft.io, by endpoint
+static SNAPCRAFT_IO_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "ratings_snapcraft_io_request_duration_seconds",
+        "The time taken for requests to snapcraft.io",
+        &["endpoint"]
+    )
+    .unwrap()
+});
+
+/// Failed requests to snapcraft.io, by endpoint
+static SNAPCRAFT_IO_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "ratings_snapcraft_io_errors_total",
+        "The number of failed requests to snapcraft.io",
+        &["endpoint"]
+    )
+    .unwrap()
+});
+
+/// Category slugs from snapcraft.io that did not match a known category. The slugs themselves are
+/// logged and quarantined in the DB rather than used as labels, as they are not a bounded set.
+static UNKNOWN_CATEGORIES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "ratings_unknown_categories_total",
+        "The number of times snapcraft.io returned a category that we do not know about"
+    )
+    .unwrap()
+});
+
+/// Connections in the DB pool, by state
+static DB_POOL_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "ratings_db_pool_connections",
+        "The number of connections in the DB pool",
+        &["state"]
+    )
+    .unwrap()
+});
+
+/// Hit and miss counts for one of our caches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Records a request being rejected by the auth middleware.
+pub fn record_auth_failure(reason: &str) {
+    AUTH_FAILURES.with_label_values(&[reason]).inc();
+}
+
+/// Records the outcome of a request to snapcraft.io.
+pub fn record_snapcraft_io_request(endpoint: &str, started: Instant, success: bool) {
+    SNAPCRAFT_IO_REQUEST_DURATION
+        .with_label_values(&[endpoint])
+        .observe(started.elapsed().as_secs_f64());
+
+    if !success {
+        SNAPCRAFT_IO_ERRORS.with_label_values(&[endpoint]).inc();
+    }
+}
+
+/// Records snapcraft.io returning a category slug that we do not know about.
+pub fn record_unknown_category() {
+    UNKNOWN_CATEGORIES.inc();
+}
+
+/// Cache stats are tracked by the caches themselves so we copy the current values into the
+/// registry each time we are scraped.
+async fn update_cache_metrics(ctx: &AppContext) {
+    for (cache, CacheStats { hits, misses }) in ctx.caches.stats().await {
+        for (result, n) in [("hit", hits), ("miss", misses)] {
+            CACHE_REQUESTS
+                .with_label_values(&[cache, result])
+                .set(n as i64);
+        }
+    }
+}
+
+/// The pool is created lazily so there is nothing to report until the first connection has been
+/// requested.
+fn update_db_pool_metrics() {
+    if let Some(pool) = db::try_get_pool() {
+        let size = pool.size() as i64;
+        let idle = pool.num_idle() as i64;
+
+        DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+        DB_POOL_CONNECTIONS
+            .with_label_values(&["active"])
+            .set(size - idle);
+    }
+}
+
+async fn metrics_handler(State(ctx): State<Arc<AppContext>>) -> impl IntoResponse {
+    update_cache_metrics(&ctx).await;
+    update_db_pool_metrics();
+
+    let mut buf = Vec::new();
+    match TextEncoder::new().encode(&prometheus::gather(), &mut buf) {
+        Ok(()) => (StatusCode::OK, buf),
+        Err(e) => {
+            error!("unable to encode metrics: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, Vec::new())
+        }
+    }
+}
+
+/// Serves the metrics endpoint over HTTP on the configured metrics port.
+pub async fn run_metrics_server(ctx: Arc<AppContext>, port: u16) -> Result<(), Box<dyn Error>> {
+    let addr: SocketAddr = format!("{}:{port}", ctx.config.host).parse()?;
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(ctx);
+
+    info!(%addr, "starting metrics server");
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+#[derive(Clone, Default)]
+pub struct MetricsLayer;
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsMiddleware { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsMiddleware<S> {
+    inner: S,
+}
+
+impl<S, T, U> Service<Request<T>> for MetricsMiddleware<S>
+where
+    S: Service<Request<T>, Response = Response<U>, Error = BoxError> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    T: Send + 'static,
+{
+    type Response = Response<U>;
+    type Error = BoxError;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<T>) -> Self::Future {
+        // See: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let clone = self.inner.clone();
+        let mut inner = replace(&mut self.inner, clone);
+
+        // Only the methods we serve are used as label values, as anyone can send requests for
+        // made-up paths and each new label value creates a new series
+        let path = req.uri().path();
+        let method = match policy_for(path) {
+            Some(_) => path.trim_start_matches('/').to_string(),
+            None => "unknown".to_string(),
+        };
+        let started = Instant::now();
+
+        Box::pin(async move {
+            let res = inner.call(req).await;
+
+            // Errors returned as part of a response are sent as a trailers-only response so the
+            // status is available in the headers. A missing status means the call succeeded.
+            let code = match &res {
+                Ok(resp) => resp
+                    .headers()
+                    .get("grpc-status")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<i32>().ok())
+                    .map(Code::from_i32)
+                    .unwrap_or(Code::Ok),
+                Err(e) => e
+                    .downcast_ref::<Status>()
+                    .map(|s| s.code())
+                    .unwrap_or(Code::Unknown),
+            };
+
+            GRPC_REQUEST_DURATION
+                .with_label_values(&[&method])
+                .observe(started.elapsed().as_secs_f64());
+            GRPC_REQUESTS
+                .with_label_values(&[&method, &format!("{code:?}")])
+                .inc();
+
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::core::Collector;
+
+    /// An inner service rejecting every request, as the auth middleware does for unknown paths
+    #[derive(Clone)]
+    struct Reject;
+
+    impl Service<Request<()>> for Reject {
+        type Response = Response<()>;
+        type Error = BoxError;
+        type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<()>) -> Self::Future {
+            Box::pin(async { Err(Box::new(Status::permission_denied("no policy")) as BoxError) })
+        }
+    }
+
+    fn recorded_methods() -> Vec<String> {
+        GRPC_REQUESTS
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric())
+            .flat_map(|metric| metric.get_label())
+            .filter(|label| label.get_name() == "method")
+            .map(|label| label.get_value().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn unknown_methods_are_not_used_as_labels() {
+        let mut service = MetricsLayer.layer(Reject);
+
+        for path in [
+            "/made.up.Service/Method",
+            "/ratings.features.user.User/Vote",
+        ] {
+            let req = Request::builder().uri(path).body(()).unwrap();
+            assert!(service.call(req).await.is_err());
+        }
+
+        let methods = recorded_methods();
+        assert!(
+            !methods.iter().any(|m| m.contains("made.up")),
+            "{methods:?}"
+        );
+        assert!(methods.iter().any(|m| m == "unknown"));
+        assert!(methods
+            .iter()
+            .any(|m| m == "ratings.features.user.User/Vote"));
+    }
+}
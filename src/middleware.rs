@@ -1,33 +1,171 @@
-//! A custom Tower [Layer] for validating jwt tokens and attaching the decoded claim to incoming
-//! requests.
-use crate::jwt::JwtVerifier;
+//! A custom Tower [Layer] enforcing the [Policy] for each RPC before its handler runs.
+//!
+//! Callers present either a jwt, whose decoded claim is attached to the request, or the
+//! [AdminCredentials] using HTTP basic authentication. Valid tokens are also checked against the
+//! [Revocations], as tokens issued to a client are revoked when it deletes its account.
+use crate::{
+    cache::Revocations,
+    db::{get_pool, User},
+    jwt::{Claims, JwtVerifier, Role},
+    metrics::record_auth_failure,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use http::{Request, Response};
+use secrecy::{ExposeSecret, SecretString};
 use std::{
+    collections::HashMap,
     error::Error,
     future::Future,
     mem::replace,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, LazyLock},
     task::{Context, Poll},
 };
+use subtle::ConstantTimeEq;
 use tonic::Status;
 use tower::{Layer, Service};
+use tracing::error;
 
 type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
 type BoxError = Box<dyn Error + Send + Sync>;
 
-/// The paths which are accessible without authentication
-pub const PUBLIC_PATHS: [&str; 1] = ["ratings.features.user.User/Authenticate"];
+/// Who is allowed to call an RPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Anyone, without authenticating
+    Public,
+    /// Any client with a valid token
+    Client,
+    /// Callers with the admin role or the [AdminCredentials]
+    Admin,
+}
+
+impl Policy {
+    /// Whether or not a token granting the given roles satisfies this policy.
+    pub fn allows(&self, roles: &[Role]) -> bool {
+        match self {
+            Self::Public | Self::Client => true,
+            Self::Admin => roles.contains(&Role::Admin),
+        }
+    }
+
+    /// Whether or not the [AdminCredentials] satisfy this policy.
+    fn accepts_admin_credentials(&self) -> bool {
+        matches!(self, Self::Admin)
+    }
+}
+
+/// The [Policy] for each RPC, keyed on the full gRPC method path. Calls to any method missing
+/// from the table are rejected.
+pub const POLICIES: [(&str, Policy); 23] = [
+    // User
+    ("/ratings.features.user.User/Authenticate", Policy::Public),
+    ("/ratings.features.user.User/RefreshToken", Policy::Public),
+    (
+        "/ratings.features.user.User/RevokeRefreshToken",
+        Policy::Public,
+    ),
+    ("/ratings.features.user.User/Delete", Policy::Client),
+    ("/ratings.features.user.User/Vote", Policy::Client),
+    ("/ratings.features.user.User/RetractVote", Policy::Client),
+    ("/ratings.features.user.User/GetSnapVotes", Policy::Client),
+    ("/ratings.features.user.User/ListMyVotes", Policy::Client),
+    // App
+    ("/ratings.features.app.App/GetRating", Policy::Client),
+    ("/ratings.features.app.App/GetBulkRatings", Policy::Client),
+    // Chart
+    ("/ratings.features.chart.Chart/GetChart", Policy::Client),
+    (
+        "/ratings.features.chart.Chart/ListCategories",
+        Policy::Client,
+    ),
+    // Admin
+    (
+        "/ratings.features.admin.Admin/GetSnapVoteCounts",
+        Policy::Admin,
+    ),
+    (
+        "/ratings.features.admin.Admin/DeleteSnapVotes",
+        Policy::Admin,
+    ),
+    (
+        "/ratings.features.admin.Admin/DeleteClientVotes",
+        Policy::Admin,
+    ),
+    ("/ratings.features.admin.Admin/BanClient", Policy::Admin),
+    ("/ratings.features.admin.Admin/UnbanClient", Policy::Admin),
+    ("/ratings.features.admin.Admin/FlushCaches", Policy::Admin),
+    (
+        "/ratings.features.admin.Admin/CreateCategory",
+        Policy::Admin,
+    ),
+    // Health and reflection
+    ("/grpc.health.v1.Health/Check", Policy::Public),
+    ("/grpc.health.v1.Health/Watch", Policy::Public),
+    (
+        "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+        Policy::Public,
+    ),
+    (
+        "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo",
+        Policy::Public,
+    ),
+];
+
+static POLICY_TABLE: LazyLock<HashMap<&str, Policy>> =
+    LazyLock::new(|| HashMap::from_iter(POLICIES));
+
+/// The [Policy] for the RPC with the given full method path, if it is one that we serve.
+pub fn policy_for(path: &str) -> Option<Policy> {
+    POLICY_TABLE.get(path).copied()
+}
+
+/// The username and password required to access the admin service, which clients provide using
+/// HTTP basic authentication.
+#[derive(Clone)]
+pub struct AdminCredentials {
+    /// The expected `user:password` pair
+    expected: SecretString,
+}
+
+impl AdminCredentials {
+    pub fn new(user: &str, password: &SecretString) -> Self {
+        Self {
+            expected: SecretString::new(format!("{user}:{}", password.expose_secret())),
+        }
+    }
+
+    /// Checks the base64 encoded `user:password` pair from a basic auth header.
+    pub fn verify(&self, encoded: &str) -> bool {
+        let Ok(decoded) = STANDARD.decode(encoded) else {
+            return false;
+        };
+
+        decoded
+            .ct_eq(self.expected.expose_secret().as_bytes())
+            .into()
+    }
+}
 
 #[derive(Clone)]
 pub struct AuthLayer {
     verifier: Arc<JwtVerifier>,
+    admin: Option<Arc<AdminCredentials>>,
+    revocations: Arc<Revocations>,
 }
 
 impl AuthLayer {
-    pub fn new(verifier: JwtVerifier) -> Self {
+    /// Creates a new layer, rejecting all requests to the admin service if no
+    /// [AdminCredentials] are provided.
+    pub fn new(
+        verifier: JwtVerifier,
+        admin: Option<AdminCredentials>,
+        revocations: Arc<Revocations>,
+    ) -> Self {
         Self {
             verifier: Arc::new(verifier),
+            admin: admin.map(Arc::new),
+            revocations,
         }
     }
 }
@@ -39,6 +177,8 @@ impl<S> Layer<S> for AuthLayer {
         AuthMiddleware {
             inner,
             verifier: self.verifier.clone(),
+            admin: self.admin.clone(),
+            revocations: self.revocations.clone(),
         }
     }
 }
@@ -47,13 +187,23 @@ impl<S> Layer<S> for AuthLayer {
 pub struct AuthMiddleware<S> {
     inner: S,
     verifier: Arc<JwtVerifier>,
+    admin: Option<Arc<AdminCredentials>>,
+    revocations: Arc<Revocations>,
 }
 
-// Helper for constructing the boxed errors we need to return from the Layer implementation below
+// Helpers for constructing the boxed errors we need to return from the Layer implementation below
 macro_rules! unauthenticated {
-    ($msg:expr) => {
+    ($reason:expr, $msg:expr) => {{
+        record_auth_failure($reason);
         Box::pin(async move { Err(Box::new(Status::unauthenticated($msg)) as BoxError) })
-    };
+    }};
+}
+
+macro_rules! permission_denied {
+    ($reason:expr, $msg:expr) => {{
+        record_auth_failure($reason);
+        Box::pin(async move { Err(Box::new(Status::permission_denied($msg)) as BoxError) })
+    }};
 }
 
 // The implementation here is based on the example provided by Tonic but with some type aliases and
@@ -79,25 +229,134 @@ where
         let clone = self.inner.clone();
         let mut inner = replace(&mut self.inner, clone);
 
-        if !PUBLIC_PATHS.iter().any(|s| req.uri().path().ends_with(s)) {
-            let header = match req.headers().get("authorization") {
-                Some(h) => h.to_str().unwrap(),
-                None => return unauthenticated!("missing auth header"),
-            };
+        let Some(policy) = policy_for(req.uri().path()) else {
+            return permission_denied!("no_policy", "no access policy for method");
+        };
+
+        if policy == Policy::Public {
+            return Box::pin(async move { inner.call(req).await });
+        }
+
+        let header = match req.headers().get("authorization") {
+            Some(h) => h.to_str().unwrap_or_default(),
+            None => return unauthenticated!("missing_header", "missing auth header"),
+        };
+
+        let parts: Vec<&str> = header.split_whitespace().collect();
+        if parts.len() != 2 {
+            return unauthenticated!("malformed_header", "malformed auth header");
+        }
+
+        if parts[0] == "Basic" {
+            let authorized = policy.accepts_admin_credentials()
+                && self
+                    .admin
+                    .as_ref()
+                    .is_some_and(|admin| admin.verify(parts[1]));
+
+            if !authorized {
+                return unauthenticated!("invalid_admin_credentials", "invalid admin credentials");
+            }
+
+            return Box::pin(async move { inner.call(req).await });
+        }
+
+        let claims = match self.verifier.decode(parts[1]) {
+            Ok(claims) => claims,
+            Err(_) => return unauthenticated!("invalid_token", "invalid auth header"),
+        };
+
+        if !policy.allows(&claims.roles) {
+            return permission_denied!("missing_role", "missing a role required by the method");
+        }
 
-            let parts: Vec<&str> = header.split_whitespace().collect();
-            if parts.len() != 2 {
-                return unauthenticated!("malformed auth header");
+        let revocations = self.revocations.clone();
+        Box::pin(async move {
+            if is_revoked(&claims, &revocations).await? {
+                record_auth_failure("revoked_token");
+                return Err(Box::new(Status::unauthenticated("token has been revoked")) as BoxError);
             }
 
-            match self.verifier.decode(parts[1]) {
-                Ok(claims) => {
-                    req.extensions_mut().insert(claims);
+            req.extensions_mut().insert(claims);
+            inner.call(req).await
+        })
+    }
+}
+
+/// Whether or not the token for the given [Claims] has been revoked, failing closed if we are
+/// unable to check. The DB is only checked while the [Revocations] are out of sync with it.
+async fn is_revoked(claims: &Claims, revocations: &Revocations) -> Result<bool, BoxError> {
+    if let Some(revoked) = revocations.is_revoked(&claims.sub, claims.issued_at()) {
+        return Ok(revoked);
+    }
+
+    let res = async {
+        let conn = &mut *get_pool().await?.acquire().await?;
+        User::is_token_revoked(&claims.sub, claims.issued_at(), conn).await
+    };
+
+    res.await.map_err(|e| {
+        error!("unable to check for revoked tokens: {e:?}");
+        Box::new(Status::unknown("Internal server error")) as BoxError
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::FILE_DESCRIPTOR_SET;
+    use prost::Message;
+    use prost_types::FileDescriptorSet;
+
+    fn credentials() -> AdminCredentials {
+        AdminCredentials::new("admin", &SecretString::new("hunter2".to_string()))
+    }
+
+    #[test]
+    fn valid_admin_credentials_are_accepted() {
+        assert!(credentials().verify(&STANDARD.encode("admin:hunter2")));
+    }
+
+    #[test]
+    fn every_rpc_has_a_policy() {
+        let descriptors = FileDescriptorSet::decode(FILE_DESCRIPTOR_SET).unwrap();
+        let mut missing = Vec::new();
+
+        for file in descriptors.file {
+            for service in &file.service {
+                for method in &service.method {
+                    let path = format!("/{}.{}/{}", file.package(), service.name(), method.name());
+                    if !POLICY_TABLE.contains_key(path.as_str()) {
+                        missing.push(path);
+                    }
                 }
-                Err(_) => return unauthenticated!("invalid auth header"),
             }
         }
 
-        Box::pin(async move { inner.call(req).await })
+        assert!(missing.is_empty(), "no policy for {missing:?}");
+    }
+
+    #[test]
+    fn policies_require_their_roles() {
+        let cases = [
+            (Policy::Client, &[][..], true),
+            (Policy::Admin, &[], false),
+            (Policy::Admin, &[Role::Publisher], false),
+            (Policy::Admin, &[Role::Admin], true),
+        ];
+
+        for (policy, roles, allowed) in cases {
+            assert_eq!(policy.allows(roles), allowed, "{policy:?} {roles:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_admin_credentials_are_rejected() {
+        let creds = credentials();
+
+        for pair in ["admin:hunter3", "root:hunter2", "admin:", "admin:hunter2 "] {
+            assert!(!creds.verify(&STANDARD.encode(pair)), "{pair:?}");
+        }
+        assert!(!creds.verify("not base64!"));
     }
 }
@@ -1,3 +1,9 @@
+/// The encoded file descriptor set for all of our services, used for gRPC server reflection
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("ratings_descriptor");
+
+pub mod admin {
+    include!("ratings.features.admin.rs");
+}
 pub mod app {
     include!("ratings.features.app.rs");
 }
@@ -4,6 +4,32 @@
 pub struct GetRatingRequest {
     #[prost(string, tag = "1")]
     pub snap_id: ::prost::alloc::string::String,
+    /// Restrict the rating to votes cast from start, inclusive, until end,
+    /// exclusive. Both must be set together.
+    #[prost(message, optional, tag = "4")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "5")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
+    /// Restrict the rating to a subset of the snap's revisions. If neither is
+    /// set then votes for all revisions are included.
+    #[prost(oneof = "get_rating_request::Revisions", tags = "2, 3")]
+    pub revisions: ::core::option::Option<get_rating_request::Revisions>,
+}
+/// Nested message and enum types in `GetRatingRequest`.
+pub mod get_rating_request {
+    /// Restrict the rating to a subset of the snap's revisions. If neither is
+    /// set then votes for all revisions are included.
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Revisions {
+        /// Only include votes cast against this specific revision
+        #[prost(int32, tag = "2")]
+        SnapRevision(i32),
+        /// Only include votes cast against the N most recent revisions that
+        /// have received votes
+        #[prost(uint32, tag = "3")]
+        LatestRevisions(u32),
+    }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
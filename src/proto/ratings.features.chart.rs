@@ -4,8 +4,26 @@
 pub struct GetChartRequest {
     #[prost(enumeration = "Timeframe", tag = "1")]
     pub timeframe: i32,
+    /// Deprecated: only covers the categories that existed when the enum was defined, use
+    /// category_slug instead. At most one of category and category_slug may be set.
     #[prost(enumeration = "Category", optional, tag = "2")]
     pub category: ::core::option::Option<i32>,
+    /// The slug of the category to restrict the chart to, as returned by ListCategories
+    #[prost(string, optional, tag = "3")]
+    pub category_slug: ::core::option::Option<::prost::alloc::string::String>,
+    /// The maximum number of entries to return, the server default is used if unset
+    #[prost(uint32, tag = "4")]
+    pub page_size: u32,
+    /// The next_page_token from a previous response, or empty for the first page
+    #[prost(string, tag = "5")]
+    pub page_token: ::prost::alloc::string::String,
+    /// Restrict the chart to votes cast from start, inclusive, until end, exclusive. Both must be
+    /// set together and the timeframe must be left unspecified when they are. The range is
+    /// applied to the precision of the given timestamps, it is not rounded to whole days.
+    #[prost(message, optional, tag = "6")]
+    pub start: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "7")]
+    pub end: ::core::option::Option<::prost_types::Timestamp>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -14,8 +32,33 @@ pub struct GetChartResponse {
     pub timeframe: i32,
     #[prost(message, repeated, tag = "2")]
     pub ordered_chart_data: ::prost::alloc::vec::Vec<super::common::ChartData>,
+    /// Only set if the category is one of those in the Category enum
     #[prost(enumeration = "Category", optional, tag = "3")]
     pub category: ::core::option::Option<i32>,
+    #[prost(string, optional, tag = "4")]
+    pub category_slug: ::core::option::Option<::prost::alloc::string::String>,
+    /// Pass this as the page_token to fetch the next page, empty if there are no more entries
+    #[prost(string, tag = "5")]
+    pub next_page_token: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListCategoriesRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListCategoriesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub categories: ::prost::alloc::vec::Vec<CategoryInfo>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CategoryInfo {
+    /// The identifier used for the category by the store, e.g. "art-and-design"
+    #[prost(string, tag = "1")]
+    pub slug: ::prost::alloc::string::String,
+    /// The human readable name of the category
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
@@ -23,6 +66,11 @@ pub enum Timeframe {
     Unspecified = 0,
     Week = 1,
     Month = 2,
+    /// Snaps whose score has risen the most over the past week compared with the week before it.
+    /// The raw_rating of each entry is the change in its score between the two weeks, and the
+    /// rating is calculated from the votes cast in the past week.
+    Trending = 3,
+    Year = 4,
 }
 impl Timeframe {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -34,6 +82,8 @@ impl Timeframe {
             Timeframe::Unspecified => "TIMEFRAME_UNSPECIFIED",
             Timeframe::Week => "TIMEFRAME_WEEK",
             Timeframe::Month => "TIMEFRAME_MONTH",
+            Timeframe::Trending => "TIMEFRAME_TRENDING",
+            Timeframe::Year => "TIMEFRAME_YEAR",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -42,15 +92,15 @@ impl Timeframe {
             "TIMEFRAME_UNSPECIFIED" => Some(Self::Unspecified),
             "TIMEFRAME_WEEK" => Some(Self::Week),
             "TIMEFRAME_MONTH" => Some(Self::Month),
+            "TIMEFRAME_TRENDING" => Some(Self::Trending),
+            "TIMEFRAME_YEAR" => Some(Self::Year),
             _ => None,
         }
     }
 }
-/// The categories that can be selected, these
-/// are taken directly from  `curl -sS -X GET --unix-socket /run/snapd.socket "<http://localhost/v2/categories"`>
-/// On 2024-02-03, it may need to be kept in sync.
-#[derive(sqlx::Type, strum::EnumString)]
-#[strum(serialize_all = "kebab_case", ascii_case_insensitive)]
+/// Deprecated: the categories that existed when this enum was defined, taken directly from
+/// `curl -sS -X GET --unix-socket /run/snapd.socket "<http://localhost/v2/categories"`> on
+/// 2024-02-03. New categories are only available through ListCategories and category slugs.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum Category {
@@ -241,6 +291,33 @@ pub mod chart_client {
                 .insert(GrpcMethod::new("ratings.features.chart.Chart", "GetChart"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn list_categories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListCategoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListCategoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ratings.features.chart.Chart/ListCategories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("ratings.features.chart.Chart", "ListCategories"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -257,6 +334,13 @@ pub mod chart_server {
             tonic::Response<super::GetChartResponse>,
             tonic::Status,
         >;
+        async fn list_categories(
+            &self,
+            request: tonic::Request<super::ListCategoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListCategoriesResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct ChartServer<T: Chart> {
@@ -381,6 +465,52 @@ pub mod chart_server {
                     };
                     Box::pin(fut)
                 }
+                "/ratings.features.chart.Chart/ListCategories" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListCategoriesSvc<T: Chart>(pub Arc<T>);
+                    impl<
+                        T: Chart,
+                    > tonic::server::UnaryService<super::ListCategoriesRequest>
+                    for ListCategoriesSvc<T> {
+                        type Response = super::ListCategoriesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListCategoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Chart>::list_categories(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListCategoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
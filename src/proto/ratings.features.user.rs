@@ -9,8 +9,24 @@ pub struct AuthenticateRequest {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AuthenticateResponse {
+    /// The access token to provide when calling authenticated RPCs
     #[prost(string, tag = "1")]
     pub token: ::prost::alloc::string::String,
+    /// A longer lived token that can be exchanged for a new access token using RefreshToken
+    #[prost(string, tag = "2")]
+    pub refresh_token: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RefreshTokenRequest {
+    #[prost(string, tag = "1")]
+    pub refresh_token: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RevokeRefreshTokenRequest {
+    #[prost(string, tag = "1")]
+    pub refresh_token: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -26,6 +42,26 @@ pub struct GetSnapVotesResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMyVotesRequest {
+    /// The maximum number of votes to return, the server default is used if unset
+    #[prost(uint32, tag = "1")]
+    pub page_size: u32,
+    /// The next_page_token from a previous response, or empty for the first page
+    #[prost(string, tag = "2")]
+    pub page_token: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMyVotesResponse {
+    /// Votes cast by the caller across all snaps, most recent first
+    #[prost(message, repeated, tag = "1")]
+    pub votes: ::prost::alloc::vec::Vec<Vote>,
+    /// Pass this as the page_token to fetch the next page, empty if there are no more votes
+    #[prost(string, tag = "2")]
+    pub next_page_token: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Vote {
     #[prost(string, tag = "1")]
     pub snap_id: ::prost::alloc::string::String,
@@ -48,6 +84,14 @@ pub struct VoteRequest {
     #[prost(bool, tag = "3")]
     pub vote_up: bool,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RetractVoteRequest {
+    #[prost(string, tag = "1")]
+    pub snap_id: ::prost::alloc::string::String,
+    #[prost(int32, tag = "2")]
+    pub snap_revision: i32,
+}
 /// Generated client implementations.
 pub mod user_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -158,6 +202,57 @@ pub mod user_client {
                 .insert(GrpcMethod::new("ratings.features.user.User", "Authenticate"));
             self.inner.unary(req, path, codec).await
         }
+        /// Exchanges a refresh token for a new access token and refresh token. Each refresh token can
+        /// only be used once.
+        pub async fn refresh_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RefreshTokenRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::AuthenticateResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ratings.features.user.User/RefreshToken",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("ratings.features.user.User", "RefreshToken"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn revoke_refresh_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RevokeRefreshTokenRequest>,
+        ) -> std::result::Result<tonic::Response<()>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ratings.features.user.User/RevokeRefreshToken",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("ratings.features.user.User", "RevokeRefreshToken"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
         pub async fn delete(
             &mut self,
             request: impl tonic::IntoRequest<()>,
@@ -202,6 +297,28 @@ pub mod user_client {
                 .insert(GrpcMethod::new("ratings.features.user.User", "Vote"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn retract_vote(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RetractVoteRequest>,
+        ) -> std::result::Result<tonic::Response<()>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ratings.features.user.User/RetractVote",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("ratings.features.user.User", "RetractVote"));
+            self.inner.unary(req, path, codec).await
+        }
         pub async fn get_snap_votes(
             &mut self,
             request: impl tonic::IntoRequest<super::GetSnapVotesRequest>,
@@ -227,6 +344,31 @@ pub mod user_client {
                 .insert(GrpcMethod::new("ratings.features.user.User", "GetSnapVotes"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn list_my_votes(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListMyVotesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListMyVotesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ratings.features.user.User/ListMyVotes",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("ratings.features.user.User", "ListMyVotes"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -243,6 +385,19 @@ pub mod user_server {
             tonic::Response<super::AuthenticateResponse>,
             tonic::Status,
         >;
+        /// Exchanges a refresh token for a new access token and refresh token. Each refresh token can
+        /// only be used once.
+        async fn refresh_token(
+            &self,
+            request: tonic::Request<super::RefreshTokenRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::AuthenticateResponse>,
+            tonic::Status,
+        >;
+        async fn revoke_refresh_token(
+            &self,
+            request: tonic::Request<super::RevokeRefreshTokenRequest>,
+        ) -> std::result::Result<tonic::Response<()>, tonic::Status>;
         async fn delete(
             &self,
             request: tonic::Request<()>,
@@ -251,6 +406,10 @@ pub mod user_server {
             &self,
             request: tonic::Request<super::VoteRequest>,
         ) -> std::result::Result<tonic::Response<()>, tonic::Status>;
+        async fn retract_vote(
+            &self,
+            request: tonic::Request<super::RetractVoteRequest>,
+        ) -> std::result::Result<tonic::Response<()>, tonic::Status>;
         async fn get_snap_votes(
             &self,
             request: tonic::Request<super::GetSnapVotesRequest>,
@@ -258,6 +417,13 @@ pub mod user_server {
             tonic::Response<super::GetSnapVotesResponse>,
             tonic::Status,
         >;
+        async fn list_my_votes(
+            &self,
+            request: tonic::Request<super::ListMyVotesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListMyVotesResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct UserServer<T: User> {
@@ -382,6 +548,96 @@ pub mod user_server {
                     };
                     Box::pin(fut)
                 }
+                "/ratings.features.user.User/RefreshToken" => {
+                    #[allow(non_camel_case_types)]
+                    struct RefreshTokenSvc<T: User>(pub Arc<T>);
+                    impl<T: User> tonic::server::UnaryService<super::RefreshTokenRequest>
+                    for RefreshTokenSvc<T> {
+                        type Response = super::AuthenticateResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RefreshTokenRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as User>::refresh_token(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RefreshTokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/ratings.features.user.User/RevokeRefreshToken" => {
+                    #[allow(non_camel_case_types)]
+                    struct RevokeRefreshTokenSvc<T: User>(pub Arc<T>);
+                    impl<
+                        T: User,
+                    > tonic::server::UnaryService<super::RevokeRefreshTokenRequest>
+                    for RevokeRefreshTokenSvc<T> {
+                        type Response = ();
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RevokeRefreshTokenRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as User>::revoke_refresh_token(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RevokeRefreshTokenSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/ratings.features.user.User/Delete" => {
                     #[allow(non_camel_case_types)]
                     struct DeleteSvc<T: User>(pub Arc<T>);
@@ -466,6 +722,50 @@ pub mod user_server {
                     };
                     Box::pin(fut)
                 }
+                "/ratings.features.user.User/RetractVote" => {
+                    #[allow(non_camel_case_types)]
+                    struct RetractVoteSvc<T: User>(pub Arc<T>);
+                    impl<T: User> tonic::server::UnaryService<super::RetractVoteRequest>
+                    for RetractVoteSvc<T> {
+                        type Response = ();
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RetractVoteRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as User>::retract_vote(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RetractVoteSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/ratings.features.user.User/GetSnapVotes" => {
                     #[allow(non_camel_case_types)]
                     struct GetSnapVotesSvc<T: User>(pub Arc<T>);
@@ -510,6 +810,50 @@ pub mod user_server {
                     };
                     Box::pin(fut)
                 }
+                "/ratings.features.user.User/ListMyVotes" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListMyVotesSvc<T: User>(pub Arc<T>);
+                    impl<T: User> tonic::server::UnaryService<super::ListMyVotesRequest>
+                    for ListMyVotesSvc<T> {
+                        type Response = super::ListMyVotesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListMyVotesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as User>::list_my_votes(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListMyVotesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
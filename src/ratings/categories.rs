@@ -1,14 +1,23 @@
 //! Updating snap categories from data in snapcraft.io
 use crate::{
-    db::{set_categories_for_snap, snap_has_categories, Category},
+    cache::{invalidate, Invalidation},
+    conn,
+    db::{
+        mark_categories_refreshed, quarantine_unknown_categories, set_categories_for_snap,
+        snap_has_categories, snaps_with_stale_categories, Category,
+    },
+    metrics::record_unknown_category,
     ratings::{get_json, get_snap_name, Error},
     Context,
 };
 use serde::Deserialize;
 use sqlx::PgConnection;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::Notify;
-use tracing::error;
+use tracing::{error, info, warn};
+
+/// How often we check for snaps whose categories need refreshing
+const CATEGORY_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 /// Update the categories for a given snap.
 ///
@@ -21,8 +30,8 @@ pub async fn update_categories(
     conn: &mut PgConnection,
 ) -> Result<(), Error> {
     // If we have categories for the requested snap in place already then skip updating.
-    // Eventually we will need to update and refresh categories over time but the assumption for now is
-    // that snap categories do not change frequently so we do not need to eagerly update them.
+    // Snap categories do not change frequently so rather than refreshing them here they are
+    // periodically re-synced in the background by [refresh_categories_periodically].
     if snap_has_categories(snap_id, conn).await? {
         return Ok(());
     }
@@ -50,8 +59,7 @@ pub async fn update_categories(
     // We can't early return while holding the Notifier as that will leave any waiting tasks
     // blocked. Rather than attempt to retry at this stage we allow for stale category data
     // until a new task attempts to get data for the same snap.
-    let base = &ctx.config.snapcraft_io_uri;
-    if let Err(e) = update_categories_inner(snap_id, base, &ctx.http_client, conn).await {
+    if let Err(e) = update_categories_inner(snap_id, ctx, conn).await {
         error!(%snap_id, "unable to update snap categories: {e}");
     }
 
@@ -67,26 +75,138 @@ pub async fn update_categories(
 #[inline]
 async fn update_categories_inner(
     snap_id: &str,
-    base: &str,
-    client: &reqwest::Client,
+    ctx: &Context,
     conn: &mut PgConnection,
 ) -> Result<(), Error> {
-    let categories = get_snap_categories(snap_id, base, client).await?;
-    if !categories.is_empty() {
-        set_categories_for_snap(snap_id, categories, conn).await?;
+    let categories = fetch_categories(snap_id, ctx, conn).await?;
+    if categories.is_empty() {
+        return Ok(());
+    }
+
+    let category_ids = categories.iter().map(|c| c.id).collect();
+    if set_categories_for_snap(snap_id, categories, conn).await? {
+        // The snap now appears in the charts for its categories
+        invalidate(ctx, &[Invalidation::Charts { category_ids }], conn).await;
     }
 
     Ok(())
 }
 
-/// Pull snap categories by for a given snapd_id from the snapcraft.io rest API
-async fn get_snap_categories(
+/// Fetches the current categories for a snap from snapcraft.io and resolves them against the
+/// categories we know about.
+///
+/// Slugs that do not match a known category are quarantined and reported rather than causing
+/// the known categories for the snap to be dropped.
+async fn fetch_categories(
     snap_id: &str,
-    base: &str,
-    client: &reqwest::Client,
+    ctx: &Context,
+    conn: &mut PgConnection,
 ) -> Result<Vec<Category>, Error> {
-    let snap_name = get_snap_name(snap_id, base, client).await?;
+    let snap_name = get_snap_name(snap_id, ctx, conn).await?;
+    let slugs =
+        get_snap_categories(&snap_name, &ctx.config.snapcraft_io_uri, &ctx.http_client).await?;
+
+    resolve_categories(snap_id, slugs, conn).await
+}
+
+/// Resolves category slugs from snapcraft.io against the categories we know about, quarantining
+/// any that we do not recognise.
+async fn resolve_categories(
+    snap_id: &str,
+    slugs: Vec<String>,
+    conn: &mut PgConnection,
+) -> Result<Vec<Category>, Error> {
+    let categories = Category::get_by_slugs(&slugs, conn).await?;
+
+    let unknown: Vec<String> = slugs
+        .into_iter()
+        .filter(|slug| !categories.iter().any(|c| &c.slug == slug))
+        .collect();
+
+    for slug in unknown.iter() {
+        warn!(%snap_id, %slug, "snapcraft.io returned an unknown category");
+        record_unknown_category();
+    }
+    quarantine_unknown_categories(snap_id, &unknown, conn).await?;
+
+    Ok(categories)
+}
+
+/// Periodically re-syncs the categories for snaps whose categories are older than the configured
+/// maximum age, so that snaps moving categories in the store also move in our charts.
+pub async fn refresh_categories_periodically(ctx: Arc<Context>) {
+    let mut interval = tokio::time::interval(CATEGORY_REFRESH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match refresh_stale_categories(&ctx).await {
+            Ok(0) => (),
+            Ok(changed) => info!(changed, "snap categories changed, flushed chart cache"),
+            Err(e) => error!("unable to refresh snap categories: {e}"),
+        }
+    }
+}
+
+/// Refreshes the categories of every snap whose categories are older than the configured maximum
+/// age, returning the number of snaps whose categories changed. The cached charts on every replica
+/// are cleared if any snaps have moved between categories.
+///
+/// Snaps whose slugs are all unknown keep their existing categories until they are next stale,
+/// while snaps that snapcraft.io no longer lists in any category are removed from the charts.
+async fn refresh_stale_categories(ctx: &Context) -> Result<usize, Error> {
+    let snap_ids = snaps_with_stale_categories(ctx.config.category_max_age(), conn!()).await?;
+    if snap_ids.is_empty() {
+        return Ok(0);
+    }
 
+    info!(snaps = snap_ids.len(), "refreshing stale snap categories");
+    let mut changed = 0;
+
+    // Snaps are refreshed one at a time to avoid flooding snapcraft.io, any that fail are
+    // retried on the next run. Connections are only held for the DB calls so that slow responses
+    // from snapcraft.io do not starve the pool.
+    for snap_id in snap_ids {
+        let res = async {
+            let snap_name = get_snap_name(&snap_id, ctx, conn!()).await?;
+            let slugs =
+                get_snap_categories(&snap_name, &ctx.config.snapcraft_io_uri, &ctx.http_client)
+                    .await?;
+
+            let conn = conn!();
+            let all_unknown = !slugs.is_empty();
+            let categories = resolve_categories(&snap_id, slugs, conn).await?;
+            if categories.is_empty() && all_unknown {
+                // Keep the existing categories rather than dropping the snap from every chart
+                // because of slugs we do not know yet, and retry once they are stale again
+                mark_categories_refreshed(&snap_id, conn).await?;
+                return Ok(false);
+            }
+
+            Ok::<_, Error>(set_categories_for_snap(&snap_id, categories, conn).await?)
+        }
+        .await;
+
+        match res {
+            Ok(true) => changed += 1,
+            Ok(false) => (),
+            Err(e) => warn!(%snap_id, "unable to refresh snap categories: {e}"),
+        }
+    }
+
+    if changed > 0 {
+        invalidate(ctx, &[Invalidation::AllCharts], conn!()).await;
+    }
+
+    Ok(changed)
+}
+
+/// Pull the slugs of the snap categories for a given snap_name from the snapcraft.io rest API
+async fn get_snap_categories(
+    snap_name: &str,
+    base: &str,
+    client: &reqwest::Client,
+) -> Result<Vec<String>, Error> {
     let base_url = reqwest::Url::parse(base).map_err(|e| Error::InvalidUrl(e.to_string()))?;
     let info_url = base_url
         .join(&format!("snaps/info/{snap_name}"))
@@ -94,14 +214,9 @@ async fn get_snap_categories(
 
     let FindResp {
         snap: SnapInfo { categories },
-    } = get_json(info_url, &[("fields", "categories")], client).await?;
-
-    let res: Result<Vec<Category>, Error> = categories
-        .into_iter()
-        .map(|c| Category::try_from(c.name.as_str()).map_err(Into::into))
-        .collect();
+    } = get_json("snap-info", info_url, &[("fields", "categories")], client).await?;
 
-    return res;
+    return Ok(categories.into_iter().map(|c| c.name).collect());
 
     // serde structs
 
@@ -132,9 +247,8 @@ mod tests {
     async fn get_snap_categories_works() {
         let client = reqwest::Client::new();
         let base = "https://api.snapcraft.io/v2/";
-        let snap_id = "NeoQngJVBf2wKC48bxnF2xqmfEFGdVnx"; // steam
-        let categories = get_snap_categories(snap_id, base, &client).await.unwrap();
+        let categories = get_snap_categories("steam", base, &client).await.unwrap();
 
-        assert_eq!(categories, vec![Category::Games]);
+        assert_eq!(categories, vec!["games"]);
     }
 }
@@ -1,9 +1,12 @@
 //! Struct definitions for the charting feature for ratings.
 use crate::{
-    db::{Timeframe, VoteSummary},
-    ratings::rating::{calculate_band, Rating},
+    db::{Timeframe, TrendingSummaries, VoteSummary},
+    ratings::{
+        rating::{calculate_band, BandThresholds, Rating},
+        RankingStrategy,
+    },
 };
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::HashMap};
 
 #[derive(Debug, Clone)]
 pub struct Chart {
@@ -12,32 +15,99 @@ pub struct Chart {
 }
 
 impl Chart {
-    pub fn new(timeframe: Timeframe, data: Vec<VoteSummary>) -> Self {
-        let mut data: Vec<ChartData> = data.into_iter().map(Into::into).collect();
+    /// Builds a chart from the given vote summaries, rated using the given [RankingStrategy] and
+    /// [BandThresholds].
+    pub fn new(
+        timeframe: Timeframe,
+        data: Vec<VoteSummary>,
+        strategy: &RankingStrategy,
+        thresholds: &BandThresholds,
+    ) -> Self {
+        let data: Vec<ChartData> = data
+            .into_iter()
+            .map(|summary| ChartData::new(summary, strategy, thresholds))
+            .collect();
 
+        Self::from_unsorted(timeframe, data)
+    }
+
+    /// Builds a [Timeframe::Trending] chart of the snaps that were voted on in the recent window,
+    /// ranked by how much their score changed compared with the previous window.
+    ///
+    /// Scores are calculated by the given [RankingStrategy] without the minimum vote threshold
+    /// used for ratings bands. As the default Wilson strategy rewards volume as well as the ratio
+    /// of positive votes, snaps move up the chart when either of these grow.
+    pub fn new_trending(
+        summaries: TrendingSummaries,
+        strategy: &RankingStrategy,
+        thresholds: &BandThresholds,
+    ) -> Self {
+        let TrendingSummaries { recent, previous } = summaries;
+        let previous: HashMap<String, f64> = previous
+            .into_iter()
+            .map(|summary| (summary.snap_id.clone(), score(&summary, strategy)))
+            .collect();
+
+        let data: Vec<ChartData> = recent
+            .into_iter()
+            .map(|summary| {
+                let change =
+                    score(&summary, strategy) - previous.get(&summary.snap_id).unwrap_or(&0.0);
+                let mut data = ChartData::new(summary, strategy, thresholds);
+                data.raw_rating = change as f32;
+
+                data
+            })
+            .collect();
+
+        Self::from_unsorted(Timeframe::Trending, data)
+    }
+
+    fn from_unsorted(timeframe: Timeframe, mut data: Vec<ChartData>) -> Self {
         data.sort_by(|a, b| {
             b.raw_rating
                 .partial_cmp(&a.raw_rating)
                 .unwrap_or(Ordering::Equal)
         });
 
-        // Take only the first 20 elements from the sorted chart_data
-        Chart {
-            timeframe,
-            data: data.into_iter().take(20).collect(),
-        }
+        Chart { timeframe, data }
+    }
+
+    /// Up to `size` entries of the chart starting from `offset`, along with the offset of the
+    /// next page if there are more entries remaining.
+    pub fn page(&self, offset: usize, size: usize) -> ChartPage {
+        let data: Vec<ChartData> = self.data.iter().skip(offset).take(size).cloned().collect();
+        let end = offset + data.len();
+        let next = (end < self.data.len()).then_some(end);
+
+        ChartPage { data, next }
     }
 }
 
+/// A single page of a [Chart].
+#[derive(Debug, Clone)]
+pub struct ChartPage {
+    /// The entries in this page, in chart order
+    pub data: Vec<ChartData>,
+    /// The offset to resume from in order to fetch the next page, if there is one
+    pub next: Option<usize>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ChartData {
     pub raw_rating: f32,
     pub rating: Rating,
 }
 
-impl From<VoteSummary> for ChartData {
-    fn from(vote_summary: VoteSummary) -> Self {
-        let (raw_rating, ratings_band) = calculate_band(&vote_summary);
+impl ChartData {
+    /// Rates a snap from its [VoteSummary] using the given [RankingStrategy] and
+    /// [BandThresholds].
+    pub fn new(
+        vote_summary: VoteSummary,
+        strategy: &RankingStrategy,
+        thresholds: &BandThresholds,
+    ) -> Self {
+        let (raw_rating, ratings_band) = calculate_band(&vote_summary, strategy, thresholds);
         let rating = Rating {
             snap_id: vote_summary.snap_id,
             total_votes: vote_summary.total_votes as u64,
@@ -48,3 +118,95 @@ impl From<VoteSummary> for ChartData {
         Self { raw_rating, rating }
     }
 }
+
+/// The score given to a snap by the given ranking strategy, regardless of how many votes it has.
+fn score(summary: &VoteSummary, strategy: &RankingStrategy) -> f64 {
+    strategy.score(
+        summary.weighted_positive_votes,
+        summary.weighted_total_votes,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ratings::RatingsBand;
+    use simple_test_case::test_case;
+
+    fn chart(n: usize) -> Chart {
+        let data = (0..n)
+            .map(|i| ChartData {
+                raw_rating: 1.0 - i as f32 / n as f32,
+                rating: Rating {
+                    snap_id: i.to_string(),
+                    total_votes: 100,
+                    ratings_band: RatingsBand::Good,
+                },
+            })
+            .collect();
+
+        Chart {
+            timeframe: Timeframe::Unspecified,
+            data,
+        }
+    }
+
+    fn summary(snap_id: &str, positive_votes: i64, total_votes: i64) -> VoteSummary {
+        VoteSummary {
+            snap_id: snap_id.to_string(),
+            total_votes,
+            positive_votes,
+            weighted_total_votes: total_votes as f64,
+            weighted_positive_votes: positive_votes as f64,
+        }
+    }
+
+    #[test]
+    fn trending_ranks_by_change_in_score() {
+        let chart = Chart::new_trending(
+            TrendingSummaries {
+                recent: vec![
+                    summary("steady", 90, 100),
+                    summary("rising", 40, 40),
+                    summary("new", 10, 10),
+                    summary("falling", 5, 20),
+                ],
+                previous: vec![
+                    summary("steady", 90, 100),
+                    summary("rising", 5, 10),
+                    summary("falling", 20, 20),
+                    summary("gone", 50, 50),
+                ],
+            },
+            &RankingStrategy::default(),
+            &BandThresholds::default(),
+        );
+
+        let ids: Vec<&str> = chart
+            .data
+            .iter()
+            .map(|d| d.rating.snap_id.as_str())
+            .collect();
+
+        assert_eq!(ids, ["new", "rising", "steady", "falling"]);
+        assert_eq!(chart.data[2].raw_rating, 0.0);
+    }
+
+    #[test_case(0, 2, &["0", "1"], Some(2); "first page")]
+    #[test_case(2, 2, &["2", "3"], Some(4); "middle page")]
+    #[test_case(4, 2, &["4"], None; "partial last page")]
+    #[test_case(3, 2, &["3", "4"], None; "exact last page")]
+    #[test_case(5, 2, &[], None; "past the end")]
+    #[test]
+    fn page_works(offset: usize, size: usize, expected: &[&str], next: Option<usize>) {
+        let page = chart(5).page(offset, size);
+        let ids: Vec<&str> = page
+            .data
+            .iter()
+            .map(|d| d.rating.snap_id.as_str())
+            .collect();
+
+        assert_eq!(ids, expected);
+        assert_eq!(page.next, next);
+    }
+}
@@ -1,13 +1,18 @@
 //! Business logic building on top of the db layer
 mod categories;
 mod charts;
+mod ranking;
 mod rating;
+mod snaps;
 
-use cached::proc_macro::cached;
-pub use categories::update_categories;
-pub use charts::{Chart, ChartData};
-pub use rating::{calculate_band, Rating, RatingsBand};
-use serde::{de::DeserializeOwned, Deserialize};
+use crate::metrics::record_snapcraft_io_request;
+pub use categories::{refresh_categories_periodically, update_categories};
+pub use charts::{Chart, ChartData, ChartPage};
+pub use ranking::{RankingStrategy, RankingStrategyKind, DEFAULT_Z_SCORE};
+pub use rating::{calculate_band, BandThresholds, Rating, RatingsBand};
+use serde::de::DeserializeOwned;
+pub use snaps::{get_snap_name, get_snap_names};
+use std::time::Instant;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -20,9 +25,6 @@ pub enum Error {
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
 
-    #[error(transparent)]
-    Strum(#[from] strum::ParseError),
-
     #[error("invalid url: {0}")]
     InvalidUrl(String),
 
@@ -33,8 +35,24 @@ pub enum Error {
     Json(#[from] serde_json::Error),
 }
 
+/// Makes a GET request to snapcraft.io and parses the response as JSON, recording the latency
+/// and outcome of the request under the given endpoint name.
 #[inline]
 async fn get_json<T: DeserializeOwned>(
+    endpoint: &str,
+    url: reqwest::Url,
+    query: &[(&str, &str)],
+    client: &reqwest::Client,
+) -> Result<T, Error> {
+    let started = Instant::now();
+    let res = get_json_inner(url, query, client).await;
+    record_snapcraft_io_request(endpoint, started, res.is_ok());
+
+    res
+}
+
+#[inline]
+async fn get_json_inner<T: DeserializeOwned>(
     url: reqwest::Url,
     query: &[(&str, &str)],
     client: &reqwest::Client,
@@ -52,41 +70,3 @@ async fn get_json<T: DeserializeOwned>(
 
     Ok(serde_json::from_str(&s)?)
 }
-
-#[cfg_attr(
-    not(feature = "skip_cache"),
-    cached(
-        key = "String",
-        convert = r##"{String::from(snap_id)}"##,
-        result = true
-    )
-)]
-pub(crate) async fn get_snap_name(
-    snap_id: &str,
-    base: &str,
-    client: &reqwest::Client,
-) -> Result<String, Error> {
-    let base_url = reqwest::Url::parse(base).map_err(|e| Error::InvalidUrl(e.to_string()))?;
-    let assertions_url = base_url
-        .join(&format!("assertions/snap-declaration/16/{snap_id}"))
-        .map_err(|e| Error::InvalidUrl(e.to_string()))?;
-
-    let AssertionsResp {
-        headers: Headers { snap_name },
-    } = get_json(assertions_url, &[], client).await?;
-
-    return Ok(snap_name);
-
-    // serde structs
-    //
-    #[derive(Debug, Deserialize)]
-    struct AssertionsResp {
-        headers: Headers,
-    }
-
-    #[derive(Debug, Deserialize)]
-    #[serde(rename_all = "kebab-case")]
-    struct Headers {
-        snap_name: String,
-    }
-}
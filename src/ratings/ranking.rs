@@ -0,0 +1,217 @@
+//! Strategies for turning a snap's votes into a raw rating that can be ranked against others
+
+/// The z-score giving a ~95% confidence for [`RankingStrategy::Wilson`].
+pub const DEFAULT_Z_SCORE: f64 = 1.96;
+
+/// The name of a [`RankingStrategy`] as it is given in [`Config`].
+///
+/// [`Config`]: crate::Config
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RankingStrategyKind {
+    #[default]
+    Wilson,
+    Bayesian,
+    Ratio,
+}
+
+/// How the positive and total vote counts for a snap are combined into a single raw rating in
+/// the range `[0, 1]`, which is then used for both ordering charts and selecting a
+/// [`RatingsBand`].
+///
+/// [`RatingsBand`]: crate::ratings::RatingsBand
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RankingStrategy {
+    /// The lower bound of the Wilson score confidence interval for the given z-score.
+    Wilson { z_score: f64 },
+    /// The average of the snap's votes combined with `prior_votes` imaginary votes at a positive
+    /// ratio of `prior_ratio`, pulling snaps with few votes towards the global prior.
+    Bayesian { prior_ratio: f64, prior_votes: f64 },
+    /// The plain ratio of positive votes to total votes.
+    Ratio,
+}
+
+impl Default for RankingStrategy {
+    fn default() -> Self {
+        Self::Wilson {
+            z_score: DEFAULT_Z_SCORE,
+        }
+    }
+}
+
+impl RankingStrategy {
+    /// Checks that the parameters for the strategy are within their valid ranges.
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Self::Wilson { z_score } if !z_score.is_finite() || z_score <= 0.0 => Err(format!(
+                "ranking z-score must be positive and finite: {z_score}"
+            )),
+            Self::Bayesian { prior_ratio, .. } if !(0.0..=1.0).contains(&prior_ratio) => Err(
+                format!("ranking prior ratio must be between 0 and 1: {prior_ratio}"),
+            ),
+            Self::Bayesian { prior_votes, .. } if !prior_votes.is_finite() || prior_votes < 0.0 => {
+                Err(format!(
+                    "ranking prior votes must not be negative and must be finite: {prior_votes}"
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Calculates the raw rating for the given, possibly weighted, vote counts.
+    pub fn score(&self, positive_votes: f64, total_votes: f64) -> f64 {
+        match *self {
+            Self::Wilson { z_score } => {
+                confidence_interval_lower_bound(positive_votes, total_votes, z_score)
+            }
+            Self::Bayesian {
+                prior_ratio,
+                prior_votes,
+            } => bayesian_average(positive_votes, total_votes, prior_ratio, prior_votes),
+            Self::Ratio if total_votes == 0.0 => 0.0,
+            Self::Ratio => positive_votes / total_votes,
+        }
+    }
+}
+
+/// Calculates the Lower Bound of Wilson Score Confidence Interval for Ranking Snaps
+///
+/// Purpose:
+/// Provides a conservative adjusted rating for a Snap by offsetting the
+/// actual ratio of positive votes. It penalizes Snaps with fewer ratings
+/// more heavily to produce an adjusted ranking that approaches the mean as
+/// ratings increase.
+///
+/// Algorithm:
+/// Starts with the observed proportion of positive ratings, adjusts it based on
+/// total ratings, and incorporates the Z-score for the desired confidence
+/// interval (1.96 for ~95%) for uncertainty.
+///
+/// References:
+/// - https://www.evanmiller.org/how-not-to-sort-by-average-rating.html
+/// - https://en.wikipedia.org/wiki/Binomial_proportion_confidence_interval#Wilson_score_interval
+fn confidence_interval_lower_bound(positive_ratings: f64, total_ratings: f64, z_score: f64) -> f64 {
+    if total_ratings == 0.0 {
+        return 0.0;
+    }
+
+    let positive_ratings_ratio = positive_ratings / total_ratings;
+    ((positive_ratings_ratio + (z_score * z_score) / (2.0 * total_ratings))
+        - z_score
+            * f64::sqrt(
+                (positive_ratings_ratio * (1.0 - positive_ratings_ratio)
+                    + ((z_score * z_score) / (4.0 * total_ratings)))
+                    / total_ratings,
+            ))
+        / (1.0 + (z_score * z_score) / total_ratings)
+}
+
+/// Calculates the Bayesian average of the positive vote ratio for a snap.
+///
+/// The observed votes are combined with `prior_votes` votes at the `prior_ratio` so that snaps
+/// with few votes sit close to the prior and only move away from it as they gather more votes.
+///
+/// References:
+/// - https://en.wikipedia.org/wiki/Bayesian_average
+fn bayesian_average(
+    positive_ratings: f64,
+    total_ratings: f64,
+    prior_ratio: f64,
+    prior_votes: f64,
+) -> f64 {
+    let denominator = prior_votes + total_ratings;
+    if denominator == 0.0 {
+        return 0.0;
+    }
+
+    (prior_ratio * prior_votes + positive_ratings) / denominator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero() {
+        let lower_bound = confidence_interval_lower_bound(0.0, 0.0, DEFAULT_Z_SCORE);
+        assert_eq!(
+            lower_bound, 0.0,
+            "Lower bound should be 0.0 when there are 0 votes"
+        );
+    }
+
+    #[test]
+    fn test_lb_approaches_true_ratio() {
+        let ratio: f64 = 0.9;
+        let mut last_lower_bound = 0.0;
+
+        for total_ratings in (100..1000).step_by(100) {
+            let total_ratings = total_ratings as f64;
+            let positive_ratings = (total_ratings * ratio).round();
+            let new_lower_bound =
+                confidence_interval_lower_bound(positive_ratings, total_ratings, DEFAULT_Z_SCORE);
+            let raw_positive_ratio = positive_ratings / total_ratings;
+
+            // As the total ratings increase, the new lower bound should be closer to the raw positive ratio.
+            assert!(
+                (raw_positive_ratio - new_lower_bound).abs() <= (raw_positive_ratio - last_lower_bound).abs(),
+                "As the number of votes goes up, the lower bound should get closer to the raw positive ratio."
+            );
+
+            last_lower_bound = new_lower_bound;
+        }
+    }
+
+    #[test]
+    fn test_bayesian_starts_at_prior() {
+        let score = bayesian_average(0.0, 0.0, 0.7, 10.0);
+        assert_eq!(score, 0.7, "With no votes the score should be the prior");
+    }
+
+    #[test]
+    fn test_bayesian_approaches_true_ratio() {
+        let strategy = RankingStrategy::Bayesian {
+            prior_ratio: 0.5,
+            prior_votes: 20.0,
+        };
+        let few = strategy.score(9.0, 10.0);
+        let many = strategy.score(900.0, 1000.0);
+
+        assert!(
+            few < many,
+            "{few} should be pulled further towards the prior than {many}"
+        );
+        assert!((0.9 - many).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_ratio() {
+        assert_eq!(RankingStrategy::Ratio.score(0.0, 0.0), 0.0);
+        assert_eq!(RankingStrategy::Ratio.score(3.0, 4.0), 0.75);
+    }
+
+    #[test]
+    fn test_invalid_parameters_are_rejected() {
+        assert!(RankingStrategy::Wilson { z_score: 0.0 }.validate().is_err());
+        assert!(RankingStrategy::Bayesian {
+            prior_ratio: 1.5,
+            prior_votes: 10.0
+        }
+        .validate()
+        .is_err());
+        assert!(RankingStrategy::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_non_finite_parameters_are_rejected() {
+        for z_score in [f64::NAN, f64::INFINITY] {
+            assert!(RankingStrategy::Wilson { z_score }.validate().is_err());
+        }
+        assert!(RankingStrategy::Bayesian {
+            prior_ratio: 0.5,
+            prior_votes: f64::NAN
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -1,8 +1,90 @@
 //! Calculations around snap ratings based on received votes
-use crate::db::VoteSummary;
+use crate::{db::VoteSummary, ratings::ranking::RankingStrategy};
 
-/// An arbitrary fixed number of votes we've determined is below the threshold to be meaningful.
-const INSUFFICIENT_VOTES_QUANTITY: i64 = 25;
+/// The break points used to map a raw rating onto a [`RatingsBand`], along with the number of
+/// votes required before a snap is rated at all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandThresholds {
+    /// The raw rating that denotes an upper bound between good and very good
+    pub good_upper: f64,
+    /// The raw rating that denotes the line between neutral and good
+    pub neutral_upper: f64,
+    /// The raw rating that denotes the line between poor and neutral
+    pub poor_upper: f64,
+    /// The raw rating that denotes a line between poor and very poor
+    pub very_poor_upper: f64,
+    /// The number of votes below which a snap is given [`RatingsBand::InsufficientVotes`]
+    pub min_votes: i64,
+}
+
+impl Default for BandThresholds {
+    fn default() -> Self {
+        Self {
+            good_upper: 0.8,
+            neutral_upper: 0.55,
+            poor_upper: 0.45,
+            very_poor_upper: 0.2,
+            min_votes: 25,
+        }
+    }
+}
+
+impl BandThresholds {
+    /// Checks that the thresholds lie within `[0, 1]` and are strictly decreasing from
+    /// `good_upper` to `very_poor_upper`, and that `min_votes` is not negative.
+    pub fn validate(&self) -> Result<(), String> {
+        let Self {
+            good_upper,
+            neutral_upper,
+            poor_upper,
+            very_poor_upper,
+            min_votes,
+        } = *self;
+
+        let thresholds = [good_upper, neutral_upper, poor_upper, very_poor_upper];
+        if thresholds.iter().any(|t| !t.is_finite()) {
+            return Err(format!("ratings band thresholds must be finite: {self:?}"));
+        }
+
+        if !(0.0..=1.0).contains(&good_upper) || !(0.0..=1.0).contains(&very_poor_upper) {
+            return Err(format!(
+                "ratings band thresholds must be between 0 and 1: {self:?}"
+            ));
+        }
+
+        if !(good_upper > neutral_upper
+            && neutral_upper > poor_upper
+            && poor_upper > very_poor_upper)
+        {
+            return Err(format!(
+                "ratings band thresholds must be strictly decreasing from good to very poor: {self:?}"
+            ));
+        }
+
+        if min_votes < 0 {
+            return Err(format!(
+                "ratings band minimum votes must not be negative: {min_votes}"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Converts a raw value into a [`RatingsBand`] value by comparing it with the thresholds.
+    pub fn band_for(&self, value: f64) -> RatingsBand {
+        if value > self.good_upper {
+            RatingsBand::VeryGood
+        } else if value > self.neutral_upper {
+            RatingsBand::Good
+        } else if value > self.poor_upper {
+            RatingsBand::Neutral
+        } else if value > self.very_poor_upper {
+            RatingsBand::Poor
+        } else {
+            RatingsBand::VeryPoor
+        }
+    }
+}
 
 /// A descriptive mapping of a number of ratings to a general indicator of "how good"
 /// an app can be said to be.
@@ -18,33 +100,6 @@ pub enum RatingsBand {
     InsufficientVotes = 5,
 }
 
-impl RatingsBand {
-    /// The percentage of votes that denotes an upper bound between good and very good
-    const GOOD_UPPER: f64 = 0.8;
-    /// The percentage of votes that denotes the line between neutral and good
-    const NEUTRAL_UPPER: f64 = 0.55;
-    /// The percentage of votes that denotes the line between poor and neutral
-    const POOR_UPPER: f64 = 0.45;
-    /// The percentage of votes that denotes a line between poor and very poor
-    const VERY_POOR_UPPER: f64 = 0.2;
-
-    /// Converts a raw value into a [`RatingsBand`] value by comparing it with the associated
-    /// constants of the struct.
-    pub fn from_value(value: f64) -> RatingsBand {
-        if value > Self::GOOD_UPPER {
-            RatingsBand::VeryGood
-        } else if value <= Self::GOOD_UPPER && value > Self::NEUTRAL_UPPER {
-            RatingsBand::Good
-        } else if value <= Self::NEUTRAL_UPPER && value > Self::POOR_UPPER {
-            RatingsBand::Neutral
-        } else if value <= Self::POOR_UPPER && value > Self::VERY_POOR_UPPER {
-            RatingsBand::Poor
-        } else {
-            RatingsBand::VeryPoor
-        }
-    }
-}
-
 impl PartialOrd for RatingsBand {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         if matches!(self, RatingsBand::InsufficientVotes)
@@ -70,9 +125,15 @@ pub struct Rating {
     pub ratings_band: RatingsBand,
 }
 
-impl From<VoteSummary> for Rating {
-    fn from(votes: VoteSummary) -> Self {
-        let (_, ratings_band) = calculate_band(&votes);
+impl Rating {
+    /// Rates a snap from its [`VoteSummary`] using the given [`RankingStrategy`] and
+    /// [`BandThresholds`].
+    pub fn new(
+        votes: VoteSummary,
+        strategy: &RankingStrategy,
+        thresholds: &BandThresholds,
+    ) -> Self {
+        let (_, ratings_band) = calculate_band(&votes, strategy, thresholds);
 
         Self {
             snap_id: votes.snap_id,
@@ -82,95 +143,39 @@ impl From<VoteSummary> for Rating {
     }
 }
 
-/// Converts a given [`VoteSummary`] into a [`RatingsBand`], if applicable, along with a
-/// confidence interval if applicable.
-pub fn calculate_band(votes: &VoteSummary) -> (Option<f64>, RatingsBand) {
-    if votes.total_votes < INSUFFICIENT_VOTES_QUANTITY {
+/// Converts a given [`VoteSummary`] into a [`RatingsBand`] using the given [`BandThresholds`],
+/// along with the raw rating calculated by the given [`RankingStrategy`] if applicable.
+pub fn calculate_band(
+    votes: &VoteSummary,
+    strategy: &RankingStrategy,
+    thresholds: &BandThresholds,
+) -> (Option<f64>, RatingsBand) {
+    if votes.total_votes < thresholds.min_votes {
         return (None, RatingsBand::InsufficientVotes);
     }
-    let adjusted_ratio = confidence_interval_lower_bound(votes.positive_votes, votes.total_votes);
+    let adjusted_ratio = strategy.score(votes.weighted_positive_votes, votes.weighted_total_votes);
 
-    (
-        Some(adjusted_ratio),
-        RatingsBand::from_value(adjusted_ratio),
-    )
-}
-
-/// Calculates the Lower Bound of Wilson Score Confidence Interval for Ranking Snaps
-///
-/// Purpose:
-/// Provides a conservative adjusted rating for a Snap by offsetting the
-/// actual ratio of positive votes. It penalizes Snaps with fewer ratings
-/// more heavily to produce an adjusted ranking that approaches the mean as
-/// ratings increase.
-///
-/// Algorithm:
-/// Starts with the observed proportion of positive ratings, adjusts it based on
-/// total ratings, and incorporates a 95% confidence interval Z-score for
-/// uncertainty.
-///
-/// References:
-/// - https://www.evanmiller.org/how-not-to-sort-by-average-rating.html
-/// - https://en.wikipedia.org/wiki/Binomial_proportion_confidence_interval#Wilson_score_interval
-fn confidence_interval_lower_bound(positive_ratings: i64, total_ratings: i64) -> f64 {
-    if total_ratings == 0 {
-        return 0.0;
-    }
-
-    let z_score: f64 = 1.96; // hardcoded for a ~95% confidence
-    let total_ratings = total_ratings as f64;
-    let positive_ratings_ratio = positive_ratings as f64 / total_ratings;
-    ((positive_ratings_ratio + (z_score * z_score) / (2.0 * total_ratings))
-        - z_score
-            * f64::sqrt(
-                (positive_ratings_ratio * (1.0 - positive_ratings_ratio)
-                    + ((z_score * z_score) / (4.0 * total_ratings)))
-                    / total_ratings,
-            ))
-        / (1.0 + (z_score * z_score) / total_ratings)
+    (Some(adjusted_ratio), thresholds.band_for(adjusted_ratio))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_zero() {
-        let lower_bound = confidence_interval_lower_bound(0, 0);
-        assert_eq!(
-            lower_bound, 0.0,
-            "Lower bound should be 0.0 when there are 0 votes"
-        );
-    }
-
-    #[test]
-    fn test_lb_approaches_true_ratio() {
-        let ratio: f64 = 0.9;
-        let mut last_lower_bound = 0.0;
-
-        for total_ratings in (100..1000).step_by(100) {
-            let positive_ratings = (total_ratings as f64 * ratio).round() as i64;
-            let new_lower_bound = confidence_interval_lower_bound(positive_ratings, total_ratings);
-            let raw_positive_ratio = positive_ratings as f64 / total_ratings as f64;
-
-            // As the total ratings increase, the new lower bound should be closer to the raw positive ratio.
-            assert!(
-                (raw_positive_ratio - new_lower_bound).abs() <= (raw_positive_ratio - last_lower_bound).abs(),
-                "As the number of votes goes up, the lower bound should get closer to the raw positive ratio."
-            );
-
-            last_lower_bound = new_lower_bound;
-        }
-    }
-
     #[test]
     fn test_insufficient_votes() {
         let votes = VoteSummary {
             snap_id: 1.to_string(),
             total_votes: 1,
             positive_votes: 1,
+            weighted_total_votes: 1.0,
+            weighted_positive_votes: 1.0,
         };
-        let (rating, band) = calculate_band(&votes);
+        let (rating, band) = calculate_band(
+            &votes,
+            &RankingStrategy::default(),
+            &BandThresholds::default(),
+        );
         assert_eq!(
             band,
             RatingsBand::InsufficientVotes,
@@ -182,14 +187,101 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_min_votes_is_configurable() {
+        let votes = VoteSummary {
+            snap_id: 1.to_string(),
+            total_votes: 5,
+            positive_votes: 5,
+            weighted_total_votes: 5.0,
+            weighted_positive_votes: 5.0,
+        };
+        let thresholds = BandThresholds {
+            min_votes: 5,
+            ..Default::default()
+        };
+
+        let (rating, band) = calculate_band(&votes, &RankingStrategy::default(), &thresholds);
+        assert!(rating.is_some());
+        assert_ne!(band, RatingsBand::InsufficientVotes);
+    }
+
+    #[test]
+    fn test_weighted_votes_are_used_for_rating() {
+        // Half of the votes are positive but they are old enough to carry very little weight
+        let votes = VoteSummary {
+            snap_id: 1.to_string(),
+            total_votes: 100,
+            positive_votes: 50,
+            weighted_total_votes: 50.0,
+            weighted_positive_votes: 5.0,
+        };
+        let (rating, band) = calculate_band(
+            &votes,
+            &RankingStrategy::default(),
+            &BandThresholds::default(),
+        );
+
+        assert!(rating < Some(0.2), "{rating:?}");
+        assert_eq!(band, RatingsBand::VeryPoor);
+    }
+
+    #[test]
+    fn test_band_for_custom_thresholds() {
+        let thresholds = BandThresholds {
+            good_upper: 0.9,
+            neutral_upper: 0.7,
+            poor_upper: 0.5,
+            very_poor_upper: 0.3,
+            min_votes: 25,
+        };
+
+        assert_eq!(thresholds.band_for(0.95), RatingsBand::VeryGood);
+        assert_eq!(thresholds.band_for(0.85), RatingsBand::Good);
+        assert_eq!(thresholds.band_for(0.6), RatingsBand::Neutral);
+        assert_eq!(thresholds.band_for(0.4), RatingsBand::Poor);
+        assert_eq!(thresholds.band_for(0.3), RatingsBand::VeryPoor);
+    }
+
+    #[test]
+    fn test_default_thresholds_are_valid() {
+        assert!(BandThresholds::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_unordered_thresholds_are_rejected() {
+        let thresholds = BandThresholds {
+            neutral_upper: 0.85,
+            ..Default::default()
+        };
+
+        assert!(thresholds.validate().is_err());
+    }
+
+    #[test]
+    fn test_nan_thresholds_are_rejected() {
+        let thresholds = BandThresholds {
+            poor_upper: f64::NAN,
+            ..Default::default()
+        };
+
+        assert!(thresholds.validate().is_err());
+    }
+
     #[test]
     fn test_sufficient_votes() {
         let votes = VoteSummary {
             snap_id: 1.to_string(),
             total_votes: 100,
             positive_votes: 100,
+            weighted_total_votes: 100.0,
+            weighted_positive_votes: 100.0,
         };
-        let (rating, band) = calculate_band(&votes);
+        let (rating, band) = calculate_band(
+            &votes,
+            &RankingStrategy::default(),
+            &BandThresholds::default(),
+        );
         assert_eq!(
             band,
             RatingsBand::VeryGood,
@@ -0,0 +1,237 @@
+//! Snap metadata from snapcraft.io, stored in the DB and refreshed once it becomes stale
+use crate::{
+    db::Snap,
+    ratings::{get_json, Error},
+    Context,
+};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use sqlx::PgConnection;
+use std::collections::{HashMap, HashSet};
+use time::OffsetDateTime;
+use tracing::warn;
+
+/// The maximum number of concurrent requests made to snapcraft.io when refreshing snap metadata
+const MAX_CONCURRENT_SNAPCRAFT_IO_REQUESTS: usize = 10;
+
+/// Resolves the name for a single snap, see [get_snap_names] for details. Unlike
+/// [get_snap_names], failing to fetch the metadata for a snap that we have not seen before is
+/// an error.
+pub async fn get_snap_name(
+    snap_id: &str,
+    ctx: &Context,
+    conn: &mut PgConnection,
+) -> Result<String, Error> {
+    let (mut names, mut failed) = resolve_snap_names([snap_id.to_string()], ctx, conn).await?;
+
+    match failed.remove(snap_id) {
+        Some(e) => Err(e),
+        None => Ok(names.remove(snap_id).expect("snap name to be resolved")),
+    }
+}
+
+/// Resolves the names for a batch of snaps using the names cached in memory and the metadata
+/// stored in the DB.
+///
+/// Snaps that we have not seen before, or whose metadata is older than the configured maximum
+/// age, are fetched from snapcraft.io with a limit on the number of requests that are in flight
+/// at any one time. If refreshing stale metadata fails then the stale name is used, and snaps
+/// that we have never been able to fetch are given an empty name, rather than failing the
+/// request.
+pub async fn get_snap_names(
+    snap_ids: impl IntoIterator<Item = String>,
+    ctx: &Context,
+    conn: &mut PgConnection,
+) -> Result<HashMap<String, String>, Error> {
+    let (mut names, failed) = resolve_snap_names(snap_ids, ctx, conn).await?;
+
+    for (snap_id, e) in failed {
+        warn!(%snap_id, "unable to fetch snap metadata, using an empty name: {e}");
+        names.insert(snap_id, String::new());
+    }
+
+    Ok(names)
+}
+
+/// Resolves the names for a batch of snaps, returning the names that could be resolved along with
+/// the errors for those that could not.
+async fn resolve_snap_names(
+    snap_ids: impl IntoIterator<Item = String>,
+    ctx: &Context,
+    conn: &mut PgConnection,
+) -> Result<(HashMap<String, String>, HashMap<String, Error>), Error> {
+    let unique: HashSet<String> = snap_ids.into_iter().collect();
+    let max_age = ctx.config.snap_metadata_max_age();
+
+    let mut names = HashMap::with_capacity(unique.len());
+    let mut uncached = Vec::new();
+    for snap_id in unique {
+        match ctx.caches.snap_names.get(&snap_id).await {
+            Some(name) => {
+                names.insert(snap_id, name);
+            }
+            None => uncached.push(snap_id),
+        }
+    }
+
+    let mut stale = HashMap::new();
+    for snap in Snap::get_by_snap_ids(&uncached, conn).await? {
+        if snap.is_stale(max_age) {
+            stale.insert(snap.snap_id.clone(), snap);
+        } else {
+            ctx.caches
+                .snap_names
+                .insert(snap.snap_id.clone(), snap.name.clone())
+                .await;
+            names.insert(snap.snap_id, snap.name);
+        }
+    }
+
+    let to_fetch: Vec<String> = uncached
+        .into_iter()
+        .filter(|snap_id| !names.contains_key(snap_id))
+        .collect();
+
+    let fetched: Vec<(String, Result<Snap, Error>)> = stream::iter(to_fetch)
+        .map(|snap_id| async move {
+            let res = fetch_snap(&snap_id, ctx).await;
+            (snap_id, res)
+        })
+        .buffer_unordered(MAX_CONCURRENT_SNAPCRAFT_IO_REQUESTS)
+        .collect()
+        .await;
+
+    let mut failed = HashMap::new();
+    for (snap_id, res) in fetched {
+        match (res, stale.remove(&snap_id)) {
+            (Ok(snap), _) => {
+                snap.save_to_db(conn).await?;
+                ctx.caches
+                    .snap_names
+                    .insert(snap_id.clone(), snap.name.clone())
+                    .await;
+                names.insert(snap_id, snap.name);
+            }
+
+            (Err(e), Some(snap)) => {
+                warn!(%snap_id, "unable to refresh snap metadata, using stale data: {e}");
+                names.insert(snap_id, snap.name);
+            }
+
+            (Err(e), None) => {
+                failed.insert(snap_id, e);
+            }
+        }
+    }
+
+    Ok((names, failed))
+}
+
+/// Pull the metadata for a given snap_id from the snapcraft.io rest API
+async fn fetch_snap(snap_id: &str, ctx: &Context) -> Result<Snap, Error> {
+    let base_url = reqwest::Url::parse(&ctx.config.snapcraft_io_uri)
+        .map_err(|e| Error::InvalidUrl(e.to_string()))?;
+    let assertions_url = base_url
+        .join(&format!("assertions/snap-declaration/16/{snap_id}"))
+        .map_err(|e| Error::InvalidUrl(e.to_string()))?;
+
+    let AssertionsResp {
+        headers: Headers {
+            snap_name,
+            publisher_id,
+        },
+    } = get_json("snap-declaration", assertions_url, &[], &ctx.http_client).await?;
+
+    return Ok(Snap {
+        snap_id: snap_id.to_string(),
+        name: snap_name,
+        publisher: publisher_id,
+        last_refreshed: OffsetDateTime::now_utc(),
+    });
+
+    // serde structs
+    //
+    #[derive(Debug, Deserialize)]
+    struct AssertionsResp {
+        headers: Headers,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct Headers {
+        snap_name: String,
+        publisher_id: Option<String>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{conn, Config};
+    use time::Duration;
+
+    /// A context whose requests to snapcraft.io always fail
+    fn offline_context() -> Context {
+        let mut config = Config::load().unwrap();
+        config.snapcraft_io_uri = "http://127.0.0.1:1/".to_string();
+
+        Context::new(config).unwrap()
+    }
+
+    fn snap(snap_id: &str, name: &str, refreshed_ago: Duration) -> Snap {
+        Snap {
+            snap_id: snap_id.to_string(),
+            name: name.to_string(),
+            publisher: None,
+            last_refreshed: OffsetDateTime::now_utc() - refreshed_ago,
+        }
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn stored_names_are_used_when_snapcraft_io_is_unavailable() -> Result<(), Error> {
+        let ctx = offline_context();
+        let conn = conn!();
+        let fresh = "10000000000000000000000000000001";
+        let stale = "10000000000000000000000000000002";
+        let unknown = "10000000000000000000000000000003";
+
+        snap(fresh, "fresh-snap", Duration::minutes(1))
+            .save_to_db(conn)
+            .await?;
+        snap(stale, "stale-snap", Duration::days(365))
+            .save_to_db(conn)
+            .await?;
+
+        let names = get_snap_names([fresh, stale, unknown].map(String::from), &ctx, conn).await?;
+
+        assert_eq!(names[fresh], "fresh-snap");
+        assert_eq!(names[stale], "stale-snap");
+        assert_eq!(names[unknown], "");
+
+        assert!(get_snap_name(unknown, &ctx, conn).await.is_err());
+        assert_eq!(get_snap_name(stale, &ctx, conn).await?, "stale-snap");
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "db_tests"), ignore)]
+    #[tokio::test]
+    async fn fresh_names_are_cached() -> Result<(), Error> {
+        let ctx = offline_context();
+        let conn = conn!();
+        let snap_id = "10000000000000000000000000000004";
+
+        snap(snap_id, "cached-snap", Duration::minutes(1))
+            .save_to_db(conn)
+            .await?;
+        get_snap_name(snap_id, &ctx, conn).await?;
+
+        assert_eq!(
+            ctx.caches.snap_names.get(snap_id).await.as_deref(),
+            Some("cached-snap")
+        );
+
+        Ok(())
+    }
+}
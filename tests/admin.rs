@@ -0,0 +1,187 @@
+pub mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::TestHelper;
+use ratings::jwt::Role;
+use simple_test_case::test_case;
+use tonic::Code;
+
+#[tokio::test]
+async fn vote_counts_are_reported_per_revision() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let creds = t.admin_credentials();
+
+    let snap_id = t.test_snap_with_initial_votes(1, 3, 2, &[]).await?;
+    t.generate_votes(&snap_id, 2, true, 4).await?;
+
+    let counts = t.get_snap_vote_counts(&snap_id, &creds).await?;
+    assert_eq!(counts.total_votes, 9);
+    assert_eq!(counts.positive_votes, 7);
+
+    let revisions: Vec<_> = counts
+        .revisions
+        .iter()
+        .map(|r| (r.snap_revision, r.total_votes, r.positive_votes))
+        .collect();
+    assert_eq!(revisions, vec![(2, 4, 4), (1, 5, 3)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn deleting_snap_votes_resets_the_rating() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let creds = t.admin_credentials();
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let snap_id = t.test_snap_with_initial_votes(1, 3, 2, &[]).await?;
+
+    let rating = t.get_rating(&snap_id, &user_token).await?;
+    assert_eq!(rating.total_votes, 5);
+
+    let deleted = t.delete_snap_votes(&snap_id, &creds).await?;
+    assert_eq!(deleted, 5);
+
+    let rating = t.get_rating(&snap_id, &user_token).await?;
+    assert_eq!(rating.total_votes, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn deleting_client_votes_only_removes_their_votes() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let creds = t.admin_credentials();
+
+    let client_hash = t.random_sha_256();
+    let user_token = t.authenticate(client_hash.clone()).await?;
+    let snap_id = t.test_snap_with_initial_votes(1, 3, 2, &[]).await?;
+    t.vote(&snap_id, 1, true, &user_token).await?;
+    t.vote(&snap_id, 2, true, &user_token).await?;
+
+    let deleted = t.delete_client_votes(&client_hash, &creds).await?;
+    assert_eq!(deleted, 2);
+
+    let rating = t.get_rating(&snap_id, &user_token).await?;
+    assert_eq!(rating.total_votes, 5);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn banned_clients_cannot_vote_or_authenticate() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let creds = t.admin_credentials();
+
+    let client_hash = t.random_sha_256();
+    let user_token = t.authenticate(client_hash.clone()).await?;
+    let snap_id = t.test_snap_with_initial_votes(1, 3, 2, &[]).await?;
+    t.vote(&snap_id, 1, true, &user_token).await?;
+
+    t.ban_client(&client_hash, true, &creds).await?;
+
+    let rating = t.get_rating(&snap_id, &user_token).await?;
+    assert_eq!(rating.total_votes, 5, "existing votes should be removed");
+
+    let err = t
+        .vote(&snap_id, 1, true, &user_token)
+        .await
+        .expect_err("banned clients should not be able to vote");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let err = t
+        .authenticate(client_hash.clone())
+        .await
+        .expect_err("banned clients should not be able to authenticate");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    t.unban_client(&client_hash, &creds).await?;
+    let user_token = t.authenticate(client_hash).await?;
+    t.vote(&snap_id, 1, true, &user_token).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn flushing_caches_succeeds() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+
+    t.flush_caches(&t.admin_credentials()).await?;
+
+    Ok(())
+}
+
+#[test_case(true; "client jwt")]
+#[test_case(false; "wrong password")]
+#[tokio::test]
+async fn admin_service_rejects_invalid_credentials(use_jwt: bool) -> anyhow::Result<()> {
+    let t = TestHelper::new();
+
+    let creds = if use_jwt {
+        t.authenticate(t.random_sha_256()).await?
+    } else {
+        STANDARD.encode("shadow:not-the-password")
+    };
+
+    let err = t
+        .flush_caches(&creds)
+        .await
+        .expect_err("the admin service should require the admin credentials");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn admin_rpcs_can_be_called_with_an_admin_token() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let token = t.role_token(t.random_sha_256(), vec![Role::Admin]);
+
+    t.flush_caches_with_token(&token).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn publisher_tokens_can_not_call_admin_rpcs() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let snap_id = t.test_snap_with_initial_votes(1, 3, 2, &[]).await?;
+    let admin_token = t.role_token(t.random_sha_256(), vec![Role::Admin]);
+
+    let counts = t
+        .get_snap_vote_counts_with_token(&snap_id, &admin_token)
+        .await?;
+    assert_eq!(counts.total_votes, 5);
+
+    let publisher_token = t.role_token(t.random_sha_256(), vec![Role::Publisher]);
+    for token in [publisher_token, t.authenticate(t.random_sha_256()).await?] {
+        let err = t
+            .get_snap_vote_counts_with_token(&snap_id, &token)
+            .await
+            .expect_err("vote counts should require the admin role");
+        let status = err
+            .downcast_ref::<tonic::Status>()
+            .expect("Error should be a tonic::Status");
+        assert_eq!(status.code(), Code::PermissionDenied);
+
+        let err = t
+            .flush_caches_with_token(&token)
+            .await
+            .expect_err("flushing caches should require the admin role");
+        let status = err
+            .downcast_ref::<tonic::Status>()
+            .expect("Error should be a tonic::Status");
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+
+    Ok(())
+}
@@ -2,6 +2,7 @@ pub mod common;
 
 use common::TestHelper;
 use simple_test_case::test_case;
+use tonic::Code;
 
 #[test_case("notarealhash"; "short")]
 #[test_case("abcdefghijkabcdefghijkabcdefghijkabcdefghijkabcdefghijkabcdefgh"; "one char too short")]
@@ -30,3 +31,69 @@ async fn valid_client_hashes_can_authenticate_multiple_times() -> anyhow::Result
 
     Ok(())
 }
+
+#[tokio::test]
+async fn refresh_tokens_can_be_exchanged_once() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let auth = t
+        .authenticate_with_refresh_token(t.random_sha_256())
+        .await?;
+    assert!(!auth.refresh_token.is_empty());
+
+    let refreshed = t.refresh_token(auth.refresh_token.clone()).await?;
+    t.assert_valid_jwt(&refreshed.token);
+    assert_ne!(refreshed.refresh_token, auth.refresh_token);
+
+    let err = t
+        .refresh_token(auth.refresh_token)
+        .await
+        .expect_err("refresh tokens can only be used once");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    t.refresh_token(refreshed.refresh_token).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn revoked_refresh_tokens_are_rejected() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let auth = t
+        .authenticate_with_refresh_token(t.random_sha_256())
+        .await?;
+
+    t.revoke_refresh_token(auth.refresh_token.clone()).await?;
+
+    let err = t
+        .refresh_token(auth.refresh_token)
+        .await
+        .expect_err("revoked refresh tokens should be rejected");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tokens_are_revoked_when_the_account_is_deleted() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let token = t.authenticate(t.random_sha_256()).await?;
+
+    t.delete_account(&token).await?;
+
+    let err = t
+        .vote(&t.random_id(), 1, true, &token)
+        .await
+        .expect_err("tokens for deleted accounts should be rejected");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    Ok(())
+}
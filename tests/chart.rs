@@ -4,9 +4,10 @@
 //       making use of any of the Categories that the tests in this file rely on.
 pub mod common;
 
-use common::{Category, TestHelper};
+use common::{TestHelper, Timeframe, Timestamp};
 use rand::{thread_rng, Rng};
 use simple_test_case::test_case;
+use tonic::Code;
 
 // !! This test expects to be the only one making use of the "Development" category
 #[tokio::test]
@@ -18,19 +19,17 @@ async fn category_chart_returns_expected_top_snap() -> anyhow::Result<()> {
         let client = t.clone();
         let (upvotes, downvotes) = random_votes(25, 50, 15, 35);
         client
-            .test_snap_with_initial_votes(1, upvotes, downvotes, &[Category::Development])
+            .test_snap_with_initial_votes(1, upvotes, downvotes, &["development"])
             .await?;
     }
 
     // A snap that should be returned as the top snap for the category
     let snap_id = t
-        .test_snap_with_initial_votes(1, 50, 0, &[Category::Development])
+        .test_snap_with_initial_votes(1, 50, 0, &["development"])
         .await?;
 
     let user_token = t.authenticate(t.random_sha_256()).await?;
-    let mut data = t
-        .get_chart(Some(Category::Development), &user_token)
-        .await?;
+    let mut data = t.get_chart(Some("development"), &user_token).await?;
 
     let top_snap = data[0].rating.take().expect("to have rating for top snap");
     assert_eq!(top_snap.snap_id, snap_id, "{top_snap:?}");
@@ -38,13 +37,13 @@ async fn category_chart_returns_expected_top_snap() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[test_case(&[(0, 25), (10, 15), (25, 0)], &[2,1,0], Category::DevicesAndIot; "Creation order is reverse rating order")]
-#[test_case(&[(27, 0), (25, 0), (26, 0)], &[0,2,1], Category::NewsAndWeather; "More positive votes is weighted higher")]
+#[test_case(&[(0, 25), (10, 15), (25, 0)], &[2,1,0], "devices-and-iot"; "Creation order is reverse rating order")]
+#[test_case(&[(27, 0), (25, 0), (26, 0)], &[0,2,1], "news-and-weather"; "More positive votes is weighted higher")]
 #[tokio::test]
 async fn category_chart_returns_expected_order(
     snap_votes: &[(u64, u64)],
     expected_order: &[usize],
-    category: Category,
+    category: &str,
 ) -> anyhow::Result<()> {
     let t = TestHelper::new();
     let mut ids = Vec::with_capacity(snap_votes.len());
@@ -72,6 +71,248 @@ async fn category_chart_returns_expected_order(
     Ok(())
 }
 
+// !! This test expects to be the only one making use of the "Productivity" category
+#[tokio::test]
+async fn category_chart_can_be_paged() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let mut ids = Vec::new();
+    for upvotes in 0..5 {
+        let id = t
+            .test_snap_with_initial_votes(1, 25 + upvotes, 0, &["productivity"])
+            .await?;
+        ids.push(id);
+    }
+    ids.reverse();
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let mut seen = Vec::new();
+    let mut page_token = String::new();
+    loop {
+        let (data, next) = t
+            .get_chart_page(
+                Timeframe::Unspecified,
+                Some("productivity"),
+                2,
+                &page_token,
+                &user_token,
+            )
+            .await?;
+        assert!(data.len() <= 2);
+        seen.extend(data.into_iter().map(|c| c.rating.unwrap().snap_id));
+
+        if next.is_empty() {
+            break;
+        }
+        page_token = next;
+    }
+
+    assert_eq!(seen, ids);
+
+    Ok(())
+}
+
+// !! This test expects to be the only one making use of the "Security" category
+#[tokio::test]
+async fn trending_chart_ranks_recent_votes() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let popular = t
+        .test_snap_with_initial_votes(1, 30, 0, &["security"])
+        .await?;
+    let unpopular = t
+        .test_snap_with_initial_votes(1, 2, 28, &["security"])
+        .await?;
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let (data, _) = t
+        .get_chart_page(Timeframe::Trending, Some("security"), 0, "", &user_token)
+        .await?;
+
+    let ids: Vec<String> = data
+        .into_iter()
+        .map(|c| c.rating.unwrap().snap_id)
+        .collect();
+    assert_eq!(ids, vec![popular, unpopular]);
+
+    Ok(())
+}
+
+// !! This test expects to be the only one making use of the "Music and Audio" category
+#[tokio::test]
+async fn category_chart_can_be_restricted_to_a_year_or_range() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let snap_id = t
+        .test_snap_with_initial_votes(1, 30, 0, &["music-and-audio"])
+        .await?;
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+
+    let data = t
+        .get_chart_in_range(
+            Timeframe::Year,
+            Some("music-and-audio"),
+            None,
+            None,
+            &user_token,
+        )
+        .await?;
+    assert_eq!(data[0].rating.as_ref().unwrap().snap_id, snap_id);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    let ts = |seconds| Some(Timestamp { seconds, nanos: 0 });
+
+    let data = t
+        .get_chart_in_range(
+            Timeframe::Unspecified,
+            Some("music-and-audio"),
+            ts(now - 3600),
+            ts(now + 3600),
+            &user_token,
+        )
+        .await?;
+    assert_eq!(data[0].rating.as_ref().unwrap().snap_id, snap_id);
+
+    let err = t
+        .get_chart_in_range(
+            Timeframe::Unspecified,
+            Some("music-and-audio"),
+            ts(now - 7200),
+            ts(now - 3600),
+            &user_token,
+        )
+        .await
+        .expect_err("no votes in the range");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::NotFound);
+
+    let err = t
+        .get_chart_in_range(
+            Timeframe::Year,
+            Some("music-and-audio"),
+            ts(now - 3600),
+            ts(now + 3600),
+            &user_token,
+        )
+        .await
+        .expect_err("timeframe and range together should be rejected");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn list_categories_includes_store_categories() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+
+    let categories = t.list_categories(&user_token).await?;
+    let games = categories
+        .iter()
+        .find(|c| c.slug == "games")
+        .expect("games to be listed");
+    assert_eq!(games.name, "Games");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_categories_can_be_charted() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let slug = format!("new-{}", t.random_id().to_lowercase());
+
+    let id = t
+        .create_category(&slug, "New Category", &t.admin_credentials())
+        .await?;
+    assert!(id > 19, "new categories are added after the original set");
+
+    let snap_id = t
+        .test_snap_with_initial_votes(1, 30, 0, &[slug.as_str()])
+        .await?;
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let mut data = t.get_chart(Some(&slug), &user_token).await?;
+    assert_eq!(data.len(), 1);
+    let rating = data[0].rating.take().expect("to have a rating");
+    assert_eq!(rating.snap_id, snap_id);
+
+    let categories = t.list_categories(&user_token).await?;
+    assert!(categories.iter().any(|c| c.slug == slug));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn voting_updates_cached_charts() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let slug = format!("new-{}", t.random_id().to_lowercase());
+    t.create_category(&slug, "New Category", &t.admin_credentials())
+        .await?;
+
+    let snap_id = t
+        .test_snap_with_initial_votes(1, 30, 0, &[slug.as_str()])
+        .await?;
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let mut data = t.get_chart(Some(&slug), &user_token).await?;
+    let rating = data[0].rating.take().expect("to have a rating");
+    assert_eq!(rating.total_votes, 30, "initial total votes");
+
+    t.vote(&snap_id, 1, true, &user_token).await?;
+
+    let mut data = t.get_chart(Some(&slug), &user_token).await?;
+    let rating = data[0].rating.take().expect("to have a rating");
+    assert_eq!(rating.total_votes, 31, "total votes after voting");
+
+    t.retract_vote(&snap_id, 1, &user_token).await?;
+
+    let mut data = t.get_chart(Some(&slug), &user_token).await?;
+    let rating = data[0].rating.take().expect("to have a rating");
+    assert_eq!(rating.total_votes, 30, "total votes after retracting");
+
+    Ok(())
+}
+
+// !! This test expects to be the only one making use of the "Science" category
+#[tokio::test]
+async fn unknown_store_categories_do_not_drop_known_ones() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let snap_id = t
+        .test_snap_with_initial_votes(1, 30, 0, &["science", "not-yet-a-category"])
+        .await?;
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let data = t.get_chart(Some("science"), &user_token).await?;
+    assert!(
+        data.iter()
+            .any(|c| c.rating.as_ref().unwrap().snap_id == snap_id),
+        "{data:?}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_category_slug_is_rejected() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+
+    let err = t
+        .get_chart(Some("not-a-category"), &user_token)
+        .await
+        .expect_err("unknown category should be rejected");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    Ok(())
+}
+
 fn random_votes(min_vote: usize, max_vote: usize, min_up: usize, max_up: usize) -> (u64, u64) {
     let mut rng = thread_rng();
     let upvotes = rng.gen_range(min_up..max_up);
@@ -1,3 +1,9 @@
 DELETE FROM snap_categories;
+DELETE FROM refresh_tokens;
 DELETE FROM users;
 DELETE FROM votes;
+DELETE FROM banned_clients;
+DELETE FROM revoked_tokens;
+DELETE FROM snaps;
+DELETE FROM unknown_categories;
+DELETE FROM daily_vote_counts;
@@ -1,20 +1,36 @@
+// tonic interceptors are required to return a bare `Status` as their error type
+#![allow(clippy::result_large_err)]
+
 use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use futures::future::join_all;
+pub use prost_types::Timestamp;
 use rand::{distributions::Alphanumeric, Rng};
+pub use ratings::proto::chart::Timeframe;
 use ratings::{
-    jwt::JwtVerifier,
+    jwt::{JwtEncoder, JwtKey, JwtKeySet, JwtVerifier, Role},
     proto::{
-        app::{app_client::AppClient, GetBulkRatingsRequest, GetRatingRequest},
-        chart::{chart_client::ChartClient, GetChartRequest, Timeframe},
+        admin::{
+            admin_client::AdminClient, BanClientRequest, CreateCategoryRequest,
+            DeleteClientVotesRequest, DeleteSnapVotesRequest, GetSnapVoteCountsRequest,
+            GetSnapVoteCountsResponse, UnbanClientRequest,
+        },
+        app::{
+            app_client::AppClient, get_rating_request::Revisions, GetBulkRatingsRequest,
+            GetRatingRequest,
+        },
+        chart::{chart_client::ChartClient, CategoryInfo, GetChartRequest, ListCategoriesRequest},
         common::ChartData,
         user::{
-            user_client::UserClient, AuthenticateRequest, GetSnapVotesRequest, Vote, VoteRequest,
+            user_client::UserClient, AuthenticateRequest, AuthenticateResponse,
+            GetSnapVotesRequest, ListMyVotesRequest, ListMyVotesResponse, RefreshTokenRequest,
+            RetractVoteRequest, RevokeRefreshTokenRequest, Vote, VoteRequest,
         },
     },
     ratings::Rating,
 };
 use reqwest::Client;
-use secrecy::SecretString;
+use secrecy::{ExposeSecret, SecretString};
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
 use std::fmt::Write;
@@ -23,9 +39,9 @@ use tonic::{
     transport::{Channel, Endpoint},
     Request,
 };
+use tonic_health::pb::{health_client::HealthClient, HealthCheckRequest};
 
 // re-export to simplify setting up test data in the test files
-pub use ratings::db::Category;
 
 // NOTE: these are set by the 'tests' Makefile target
 const MOCK_ADMIN_URL: Option<&str> = option_env!("MOCK_ADMIN_URL");
@@ -34,8 +50,12 @@ const PORT: Option<&str> = option_env!("PORT");
 
 macro_rules! client {
     ($client:ident, $channel:expr, $token:expr) => {
+        client!($client, $channel, "Bearer", $token)
+    };
+
+    ($client:ident, $channel:expr, $scheme:literal, $token:expr) => {
         $client::with_interceptor($channel, move |mut req: Request<()>| {
-            let header: MetadataValue<_> = format!("Bearer {}", $token).parse().unwrap();
+            let header: MetadataValue<_> = format!("{} {}", $scheme, $token).parse().unwrap();
             req.metadata_mut().insert("authorization", header);
 
             Ok(req)
@@ -88,19 +108,53 @@ impl TestHelper {
         }
     }
 
+    /// A token for the given client hash granting the given roles, as issued by a trusted service
+    pub fn role_token(&self, client_hash: String, roles: Vec<Role>) -> String {
+        dotenvy::dotenv().ok();
+        let JwtConfig { jwt_secret } = envy::prefixed("APP_").from_env::<JwtConfig>().unwrap();
+        let keys = JwtKeySet::new(vec![JwtKey::hmac(None, jwt_secret)], None).unwrap();
+        let encoder = JwtEncoder::new(&keys, time::Duration::hours(1)).unwrap();
+
+        return encoder.encode_with_roles(client_hash, roles).unwrap();
+
+        // serde structs
+        #[derive(Deserialize)]
+        struct JwtConfig {
+            jwt_secret: SecretString,
+        }
+    }
+
+    /// The base64 encoded admin credentials used for basic auth with the admin service
+    pub fn admin_credentials(&self) -> String {
+        #[derive(Deserialize)]
+        struct AdminConfig {
+            admin_user: String,
+            admin_password: SecretString,
+        }
+
+        dotenvy::dotenv().ok();
+        let AdminConfig {
+            admin_user,
+            admin_password,
+        } = envy::prefixed("APP_")
+            .from_env::<AdminConfig>()
+            .expect("the admin credentials need to be set");
+
+        STANDARD.encode(format!("{admin_user}:{}", admin_password.expose_secret()))
+    }
+
     /// NOTE: total needs to be above 25 in order to generate a rating
     pub async fn test_snap_with_initial_votes(
         &self,
         revision: i32,
         upvotes: u64,
         downvotes: u64,
-        categories: &[Category],
+        categories: &[&str],
     ) -> anyhow::Result<String> {
         let snap_id = self.random_id();
-        let str_categories: Vec<String> = categories.iter().map(|c| c.to_string()).collect();
         self.client
             .post(format!("{}/{}", self.mock_admin_url, snap_id))
-            .body(str_categories.join(","))
+            .body(categories.join(","))
             .send()
             .await?;
 
@@ -189,9 +243,42 @@ impl TestHelper {
     }
 
     pub async fn get_rating(&self, id: &str, token: &str) -> anyhow::Result<Rating> {
+        self.get_rating_for_revisions(id, None, token).await
+    }
+
+    pub async fn get_rating_for_revisions(
+        &self,
+        id: &str,
+        revisions: Option<Revisions>,
+        token: &str,
+    ) -> anyhow::Result<Rating> {
+        self.get_rating_with(id, revisions, None, None, token).await
+    }
+
+    pub async fn get_rating_in_range(
+        &self,
+        id: &str,
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
+        token: &str,
+    ) -> anyhow::Result<Rating> {
+        self.get_rating_with(id, None, start, end, token).await
+    }
+
+    async fn get_rating_with(
+        &self,
+        id: &str,
+        revisions: Option<Revisions>,
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
+        token: &str,
+    ) -> anyhow::Result<Rating> {
         let resp = client!(AppClient, self.channel().await, token)
             .get_rating(GetRatingRequest {
                 snap_id: id.to_string(),
+                revisions,
+                start,
+                end,
             })
             .await?
             .into_inner();
@@ -216,13 +303,33 @@ impl TestHelper {
 
     pub async fn get_chart(
         &self,
-        category: Option<Category>,
+        category: Option<&str>,
+        token: &str,
+    ) -> anyhow::Result<Vec<ChartData>> {
+        let (data, _) = self
+            .get_chart_page(Timeframe::Unspecified, category, 0, "", token)
+            .await?;
+
+        Ok(data)
+    }
+
+    pub async fn get_chart_in_range(
+        &self,
+        timeframe: Timeframe,
+        category: Option<&str>,
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
         token: &str,
     ) -> anyhow::Result<Vec<ChartData>> {
         let resp = client!(ChartClient, self.channel().await, token)
             .get_chart(GetChartRequest {
-                timeframe: Timeframe::Unspecified.into(),
-                category: category.map(|v| v as i32),
+                timeframe: timeframe.into(),
+                category: None,
+                category_slug: category.map(String::from),
+                page_size: 0,
+                page_token: String::new(),
+                start,
+                end,
             })
             .await?
             .into_inner();
@@ -230,6 +337,39 @@ impl TestHelper {
         Ok(resp.ordered_chart_data)
     }
 
+    pub async fn get_chart_page(
+        &self,
+        timeframe: Timeframe,
+        category: Option<&str>,
+        page_size: u32,
+        page_token: &str,
+        token: &str,
+    ) -> anyhow::Result<(Vec<ChartData>, String)> {
+        let resp = client!(ChartClient, self.channel().await, token)
+            .get_chart(GetChartRequest {
+                timeframe: timeframe.into(),
+                category: None,
+                category_slug: category.map(String::from),
+                page_size,
+                page_token: page_token.to_string(),
+                start: None,
+                end: None,
+            })
+            .await?
+            .into_inner();
+
+        Ok((resp.ordered_chart_data, resp.next_page_token))
+    }
+
+    pub async fn list_categories(&self, token: &str) -> anyhow::Result<Vec<CategoryInfo>> {
+        let resp = client!(ChartClient, self.channel().await, token)
+            .list_categories(ListCategoriesRequest {})
+            .await?
+            .into_inner();
+
+        Ok(resp.categories)
+    }
+
     pub async fn vote(
         &self,
         snap_id: &str,
@@ -248,6 +388,30 @@ impl TestHelper {
         Ok(())
     }
 
+    pub async fn delete_account(&self, token: &str) -> anyhow::Result<()> {
+        client!(UserClient, self.channel().await, token)
+            .delete(())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn retract_vote(
+        &self,
+        snap_id: &str,
+        snap_revision: i32,
+        token: &str,
+    ) -> anyhow::Result<()> {
+        client!(UserClient, self.channel().await, token)
+            .retract_vote(RetractVoteRequest {
+                snap_id: snap_id.to_string(),
+                snap_revision,
+            })
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn get_snap_votes(
         &self,
         token: &str,
@@ -261,13 +425,189 @@ impl TestHelper {
         Ok(resp.votes)
     }
 
+    pub async fn list_my_votes(
+        &self,
+        token: &str,
+        page_size: u32,
+        page_token: String,
+    ) -> anyhow::Result<ListMyVotesResponse> {
+        let resp = client!(UserClient, self.channel().await, token)
+            .list_my_votes(ListMyVotesRequest {
+                page_size,
+                page_token,
+            })
+            .await?
+            .into_inner();
+
+        Ok(resp)
+    }
+
+    /// Calls the standard gRPC health check without any authentication, returning the raw
+    /// serving status.
+    pub async fn health_check(&self, service: &str) -> anyhow::Result<i32> {
+        let resp = HealthClient::new(self.channel().await)
+            .check(HealthCheckRequest {
+                service: service.to_string(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(resp.status)
+    }
+
+    pub async fn get_snap_vote_counts(
+        &self,
+        snap_id: &str,
+        credentials: &str,
+    ) -> anyhow::Result<GetSnapVoteCountsResponse> {
+        let resp = client!(AdminClient, self.channel().await, "Basic", credentials)
+            .get_snap_vote_counts(GetSnapVoteCountsRequest {
+                snap_id: snap_id.to_string(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(resp)
+    }
+
+    pub async fn delete_snap_votes(&self, snap_id: &str, credentials: &str) -> anyhow::Result<u64> {
+        let resp = client!(AdminClient, self.channel().await, "Basic", credentials)
+            .delete_snap_votes(DeleteSnapVotesRequest {
+                snap_id: snap_id.to_string(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(resp.deleted_votes)
+    }
+
+    pub async fn delete_client_votes(
+        &self,
+        client_hash: &str,
+        credentials: &str,
+    ) -> anyhow::Result<u64> {
+        let resp = client!(AdminClient, self.channel().await, "Basic", credentials)
+            .delete_client_votes(DeleteClientVotesRequest {
+                client_hash: client_hash.to_string(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(resp.deleted_votes)
+    }
+
+    pub async fn ban_client(
+        &self,
+        client_hash: &str,
+        delete_votes: bool,
+        credentials: &str,
+    ) -> anyhow::Result<()> {
+        client!(AdminClient, self.channel().await, "Basic", credentials)
+            .ban_client(BanClientRequest {
+                client_hash: client_hash.to_string(),
+                delete_votes,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn unban_client(&self, client_hash: &str, credentials: &str) -> anyhow::Result<()> {
+        client!(AdminClient, self.channel().await, "Basic", credentials)
+            .unban_client(UnbanClientRequest {
+                client_hash: client_hash.to_string(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_category(
+        &self,
+        slug: &str,
+        name: &str,
+        credentials: &str,
+    ) -> anyhow::Result<i32> {
+        let resp = client!(AdminClient, self.channel().await, "Basic", credentials)
+            .create_category(CreateCategoryRequest {
+                slug: slug.to_string(),
+                name: name.to_string(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(resp.id)
+    }
+
+    pub async fn flush_caches(&self, credentials: &str) -> anyhow::Result<()> {
+        client!(AdminClient, self.channel().await, "Basic", credentials)
+            .flush_caches(())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn flush_caches_with_token(&self, token: &str) -> anyhow::Result<()> {
+        client!(AdminClient, self.channel().await, token)
+            .flush_caches(())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_snap_vote_counts_with_token(
+        &self,
+        snap_id: &str,
+        token: &str,
+    ) -> anyhow::Result<GetSnapVoteCountsResponse> {
+        let resp = client!(AdminClient, self.channel().await, token)
+            .get_snap_vote_counts(GetSnapVoteCountsRequest {
+                snap_id: snap_id.to_string(),
+            })
+            .await?
+            .into_inner();
+
+        Ok(resp)
+    }
+
     pub async fn authenticate(&self, id: String) -> anyhow::Result<String> {
+        let resp = self.authenticate_with_refresh_token(id).await?;
+
+        Ok(resp.token)
+    }
+
+    pub async fn authenticate_with_refresh_token(
+        &self,
+        id: String,
+    ) -> anyhow::Result<AuthenticateResponse> {
         let resp = UserClient::connect(self.server_url.clone())
             .await?
             .authenticate(AuthenticateRequest { id })
             .await?
             .into_inner();
 
-        Ok(resp.token)
+        Ok(resp)
+    }
+
+    pub async fn refresh_token(
+        &self,
+        refresh_token: String,
+    ) -> anyhow::Result<AuthenticateResponse> {
+        let resp = UserClient::connect(self.server_url.clone())
+            .await?
+            .refresh_token(RefreshTokenRequest { refresh_token })
+            .await?
+            .into_inner();
+
+        Ok(resp)
+    }
+
+    pub async fn revoke_refresh_token(&self, refresh_token: String) -> anyhow::Result<()> {
+        UserClient::connect(self.server_url.clone())
+            .await?
+            .revoke_refresh_token(RevokeRefreshTokenRequest { refresh_token })
+            .await?;
+
+        Ok(())
     }
 }
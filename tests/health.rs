@@ -0,0 +1,20 @@
+pub mod common;
+
+use common::TestHelper;
+use simple_test_case::test_case;
+use tonic_health::ServingStatus;
+
+#[test_case(""; "server")]
+#[test_case("ratings.features.admin.Admin"; "admin service")]
+#[test_case("ratings.features.app.App"; "app service")]
+#[test_case("ratings.features.chart.Chart"; "chart service")]
+#[test_case("ratings.features.user.User"; "user service")]
+#[tokio::test]
+async fn health_check_reports_serving_without_auth(service: &str) -> anyhow::Result<()> {
+    let t = TestHelper::new();
+
+    let status = t.health_check(service).await?;
+    assert_eq!(status, ServingStatus::Serving as i32);
+
+    Ok(())
+}
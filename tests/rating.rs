@@ -1,8 +1,53 @@
 pub mod common;
 
-use common::TestHelper;
+use common::{TestHelper, Timestamp};
+use ratings::proto::app::get_rating_request::Revisions;
+use simple_test_case::test_case;
 use tonic::Code;
 
+#[test_case(None, 45; "all revisions")]
+#[test_case(Some(Revisions::SnapRevision(1)), 25; "first revision")]
+#[test_case(Some(Revisions::SnapRevision(3)), 5; "third revision")]
+#[test_case(Some(Revisions::LatestRevisions(2)), 20; "latest two revisions")]
+#[tokio::test]
+async fn get_rating_for_revisions(
+    revisions: Option<Revisions>,
+    expected_votes: u64,
+) -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+
+    let snap_id = t.test_snap_with_initial_votes(1, 20, 5, &[]).await?;
+    t.generate_votes(&snap_id, 2, false, 15).await?;
+    t.generate_votes(&snap_id, 3, true, 5).await?;
+
+    let rating = t
+        .get_rating_for_revisions(&snap_id, revisions, &user_token)
+        .await?;
+    assert_eq!(rating.total_votes, expected_votes);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn get_rating_rejects_invalid_revisions() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let snap_id = t.test_snap_with_initial_votes(1, 5, 0, &[]).await?;
+
+    let result = t
+        .get_rating_for_revisions(&snap_id, Some(Revisions::SnapRevision(0)), &user_token)
+        .await;
+
+    let err = result.expect_err("revision 0 should be rejected");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn get_bulk_ratings_success() -> anyhow::Result<()> {
     let t = TestHelper::new();
@@ -18,7 +63,7 @@ async fn get_bulk_ratings_success() -> anyhow::Result<()> {
 
     let chart_data_1 = ratings
         .iter()
-        .find(|cd| cd.rating.as_ref().map_or(false, |r| r.snap_id == snap_id_1))
+        .find(|cd| cd.rating.as_ref().is_some_and(|r| r.snap_id == snap_id_1))
         .expect("Chart data for snap_id_1 not found");
 
     let rating_1 = chart_data_1.rating.as_ref().unwrap();
@@ -27,7 +72,7 @@ async fn get_bulk_ratings_success() -> anyhow::Result<()> {
 
     let chart_data_2 = ratings
         .iter()
-        .find(|cd| cd.rating.as_ref().map_or(false, |r| r.snap_id == snap_id_2))
+        .find(|cd| cd.rating.as_ref().is_some_and(|r| r.snap_id == snap_id_2))
         .expect("Chart data for snap_id_2 not found");
 
     let rating_2 = chart_data_2.rating.as_ref().unwrap();
@@ -105,3 +150,59 @@ async fn get_bulk_ratings_invalid_argument_too_many_ids() -> anyhow::Result<()>
 
     Ok(())
 }
+
+fn timestamp_from_now(offset_secs: i64) -> Option<Timestamp> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    Some(Timestamp {
+        seconds: now + offset_secs,
+        nanos: 0,
+    })
+}
+
+#[test_case(-3600, 3600, 30; "range including now")]
+#[test_case(-7200, -3600, 0; "range in the past")]
+#[tokio::test]
+async fn get_rating_in_range(start: i64, end: i64, expected_votes: u64) -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let snap_id = t.test_snap_with_initial_votes(1, 25, 5, &[]).await?;
+
+    let rating = t
+        .get_rating_in_range(
+            &snap_id,
+            timestamp_from_now(start),
+            timestamp_from_now(end),
+            &user_token,
+        )
+        .await?;
+    assert_eq!(rating.total_votes, expected_votes);
+
+    Ok(())
+}
+
+#[test_case(timestamp_from_now(3600), timestamp_from_now(-3600); "start after end")]
+#[test_case(timestamp_from_now(-3600), None; "missing end")]
+#[tokio::test]
+async fn get_rating_rejects_invalid_ranges(
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+) -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let snap_id = t.test_snap_with_initial_votes(1, 5, 0, &[]).await?;
+
+    let err = t
+        .get_rating_in_range(&snap_id, start, end, &user_token)
+        .await
+        .expect_err("invalid range should be rejected");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    Ok(())
+}
@@ -1,8 +1,10 @@
 pub mod common;
 
-use common::{Category, TestHelper};
+use common::TestHelper;
+use ratings::proto::user::GetSnapVotesRequest;
 use ratings::ratings::RatingsBand::{self, *};
 use simple_test_case::test_case;
+use tonic::Code;
 
 #[test_case(true; "up vote")]
 #[test_case(false; "down vote")]
@@ -13,7 +15,7 @@ async fn voting_increases_vote_count(vote_up: bool) -> anyhow::Result<()> {
     let user_token = t.authenticate(t.random_sha_256()).await?;
     let snap_revision = 1;
     let snap_id = t
-        .test_snap_with_initial_votes(snap_revision, 3, 2, &[Category::Social])
+        .test_snap_with_initial_votes(snap_revision, 3, 2, &["social"])
         .await?;
 
     let initial_rating = t.get_rating(&snap_id, &user_token).await?;
@@ -29,6 +31,31 @@ async fn voting_increases_vote_count(vote_up: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn votes_are_visible_after_concurrent_reads() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let snap_revision = 1;
+    let snap_id = t
+        .test_snap_with_initial_votes(snap_revision, 3, 2, &["social"])
+        .await?;
+
+    // Reads that start before a vote is saved must not leave its stale rating in the cache
+    for expected in 6..=10 {
+        let user_token = t.authenticate(t.random_sha_256()).await?;
+        let (vote, _, _) = tokio::join!(
+            t.vote(&snap_id, snap_revision, true, &user_token),
+            t.get_rating(&snap_id, &user_token),
+            t.get_rating(&snap_id, &user_token),
+        );
+        vote?;
+
+        let rating = t.get_rating(&snap_id, &user_token).await?;
+        assert_eq!(rating.total_votes, expected, "total votes after the vote");
+    }
+
+    Ok(())
+}
+
 #[test_case(true; "up to down vote")]
 #[test_case(false; "down to up vote")]
 #[tokio::test]
@@ -38,7 +65,7 @@ async fn changing_your_vote_doesnt_alter_total(initial_up: bool) -> anyhow::Resu
     let user_token = t.authenticate(t.random_sha_256()).await?;
     let snap_revision = 1;
     let snap_id = t
-        .test_snap_with_initial_votes(snap_revision, 3, 2, &[Category::Social])
+        .test_snap_with_initial_votes(snap_revision, 3, 2, &["social"])
         .await?;
 
     let initial_rating = t.get_rating(&snap_id, &user_token).await?;
@@ -84,7 +111,7 @@ async fn voting_updates_ratings_band(
     let user_token = t.authenticate(t.random_sha_256()).await?;
     let snap_revision = 1;
     let snap_id = t
-        .test_snap_with_initial_votes(snap_revision, 60, 40, &[Category::Games])
+        .test_snap_with_initial_votes(snap_revision, 60, 40, &["games"])
         .await?;
 
     let r = t.get_rating(&snap_id, &user_token).await?;
@@ -120,3 +147,105 @@ async fn voting_on_a_snap_without_categories_works() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn retracting_a_vote_removes_it() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let snap_revision = 1;
+    let snap_id = t
+        .test_snap_with_initial_votes(snap_revision, 3, 2, &["social"])
+        .await?;
+
+    t.vote(&snap_id, snap_revision, true, &user_token).await?;
+    let rating = t.get_rating(&snap_id, &user_token).await?;
+    assert_eq!(rating.total_votes, 6, "total votes after voting");
+
+    t.retract_vote(&snap_id, snap_revision, &user_token).await?;
+    let rating = t.get_rating(&snap_id, &user_token).await?;
+    assert_eq!(rating.total_votes, 5, "total votes after retracting");
+
+    let votes = t
+        .get_snap_votes(
+            &user_token,
+            GetSnapVotesRequest {
+                snap_id: snap_id.clone(),
+            },
+        )
+        .await?;
+    assert!(votes.is_empty(), "{votes:?}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn retracting_a_missing_vote_is_not_found() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let snap_id = t.test_snap_with_initial_votes(1, 3, 2, &[]).await?;
+
+    let err = t
+        .retract_vote(&snap_id, 1, &user_token)
+        .await
+        .expect_err("there is no vote to retract");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::NotFound);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn listing_my_votes_pages_through_all_snaps() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+    let mut snap_ids = Vec::new();
+    for _ in 0..3 {
+        let snap_id = t.test_snap_with_initial_votes(1, 0, 0, &[]).await?;
+        t.vote(&snap_id, 1, true, &user_token).await?;
+        snap_ids.push(snap_id);
+    }
+
+    let first = t.list_my_votes(&user_token, 2, String::new()).await?;
+    assert_eq!(first.votes.len(), 2);
+    assert!(!first.next_page_token.is_empty());
+
+    let second = t
+        .list_my_votes(&user_token, 2, first.next_page_token)
+        .await?;
+    assert_eq!(second.votes.len(), 1);
+    assert!(second.next_page_token.is_empty());
+
+    // Votes are returned most recent first
+    let listed: Vec<String> = first
+        .votes
+        .into_iter()
+        .chain(second.votes)
+        .map(|v| v.snap_id)
+        .collect();
+    snap_ids.reverse();
+    assert_eq!(listed, snap_ids);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn listing_my_votes_rejects_invalid_page_tokens() -> anyhow::Result<()> {
+    let t = TestHelper::new();
+    let user_token = t.authenticate(t.random_sha_256()).await?;
+
+    let err = t
+        .list_my_votes(&user_token, 0, "not-a-token".to_string())
+        .await
+        .expect_err("invalid page token should be rejected");
+    let status = err
+        .downcast_ref::<tonic::Status>()
+        .expect("Error should be a tonic::Status");
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    Ok(())
+}